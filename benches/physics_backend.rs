@@ -0,0 +1,96 @@
+//! Benchmark for compare the physics backends on the `maps/debug.glb` terrain.
+//!
+//! Rapier: `cargo bench --bench physics_backend`
+//! XPBD:   `cargo bench --bench physics_backend --no-default-features --features physics-xpbd`
+
+#[path = "../src/physics/mod.rs"]
+#[allow(dead_code)]
+mod physics;
+
+use std::time::{Duration, Instant};
+use bevy::gltf::{Gltf, GltfMesh, GltfNode};
+use bevy::prelude::*;
+use bevy::render::settings::{RenderCreation, WgpuSettings};
+use bevy::render::RenderPlugin;
+use bevy::window::ExitCondition;
+use bevy::winit::WinitPlugin;
+use crate::physics::{player_body, terrain_body, PhysicsBackendPlugin, BACKEND_NAME};
+
+const BODY_GRID: i32 = 16;
+const STEPS: u32 = 600;
+const LOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn main() {
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins.set(
+        WindowPlugin {
+            primary_window: None,
+            exit_condition: ExitCondition::DontExit,
+            close_when_requested: false,
+        }
+    ).set(
+        RenderPlugin {
+            render_creation: RenderCreation::Automatic(WgpuSettings {
+                backends: None,
+                ..default()
+            }),
+            ..default()
+        }
+    ).disable::<WinitPlugin>()).add_plugins(PhysicsBackendPlugin);
+
+    let handle: Handle<Gltf> = app.world().resource::<AssetServer>().load("maps/debug.glb");
+    let load_start = Instant::now();
+    while !app.world().resource::<AssetServer>().is_loaded_with_dependencies(&handle) {
+        assert!(load_start.elapsed() < LOAD_TIMEOUT, "maps/debug.glb was not loaded in time");
+        app.update();
+    }
+
+    let build_start = Instant::now();
+    let terrain_count = spawn_terrain(&mut app, &handle);
+    let build_time = build_start.elapsed();
+
+    for x in 0..BODY_GRID {
+        for z in 0..BODY_GRID {
+            app.world_mut().spawn((
+                TransformBundle::from_transform(Transform::from_xyz(x as f32 * 2.0, 30.0, z as f32 * 2.0)),
+                player_body(Vec3::splat(0.5)),
+            ));
+        }
+    }
+
+    let step_start = Instant::now();
+    for _ in 0..STEPS {
+        app.update();
+    }
+    let step_time = step_start.elapsed();
+
+    println!("[{}] {} terrain colliders build in {:?}", BACKEND_NAME, terrain_count, build_time);
+    println!("[{}] {} steps with {} bodies in {:?} ({:?} per step)",
+             BACKEND_NAME, STEPS, BODY_GRID * BODY_GRID, step_time, step_time / STEPS);
+}
+
+/// Spawn every terrain node of the area like the chunk handler does and
+/// return the amount of created colliders.
+fn spawn_terrain(app: &mut App, handle: &Handle<Gltf>) -> usize {
+    let world = app.world_mut();
+    let mut bundles = Vec::new();
+
+    let gltf = world.resource::<Assets<Gltf>>().get(handle).expect("debug.glb is loaded");
+    for node_handle in gltf.nodes.iter() {
+        let Some(node) = world.resource::<Assets<GltfNode>>().get(node_handle) else { continue };
+        if !node.name.contains("terrain") {
+            continue;
+        }
+
+        let Some(gltf_mesh) = node.mesh.as_ref().and_then(|mesh| world.resource::<Assets<GltfMesh>>().get(mesh)) else { continue };
+        for primitive in gltf_mesh.primitives.iter() {
+            if let Some(body) = world.resource::<Assets<Mesh>>().get(&primitive.mesh).and_then(terrain_body) {
+                bundles.push((TransformBundle::from_transform(node.transform), body));
+            }
+        }
+    }
+
+    let count = bundles.len();
+    world.spawn_batch(bundles);
+    count
+}
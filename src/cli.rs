@@ -0,0 +1,213 @@
+use std::path::PathBuf;
+use bevy::log::Level;
+use bevy::prelude::*;
+use clap::Parser;
+use crate::entities::player::PlayerSpawn;
+use crate::environment::AreaFile;
+use crate::headless::{HeadlessConfig, HeadlessExit, DEFAULT_TICK_RATE};
+use crate::logic::{GameSettings, InputRecordPlugin, InputRecording, InputReplayPlugin, LoadRequest, RenderBackend};
+use crate::manager::DebugOptions;
+
+//################################################# Models #################################################
+/// Command line arguments of the game binary, see `mira-game --help`.
+#[derive(Parser, Debug, Clone, PartialEq)]
+#[command(name = "mira-game", version, about = "Mira game client and headless simulation")]
+pub struct Cli {
+    /// Area glb or area index inside of the assets folder, like `maps/debug.glb`
+    /// or `maps/forest/forest.area.toml`, or `procedural:<seed>`.
+    #[arg(long, value_name = "FILE", value_parser = parse_area)]
+    pub area: Option<String>,
+
+    /// Spawn position of the player.
+    #[arg(long, value_name = "X,Y,Z", value_parser = parse_position, allow_hyphen_values = true)]
+    pub spawn: Option<Vec3>,
+
+    /// Save slot which is loaded after the start.
+    #[arg(long, value_name = "SLOT")]
+    pub slot: Option<u8>,
+
+    /// Window width, overrides the settings file.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(320..=7680))]
+    pub width: Option<u32>,
+
+    /// Window height, overrides the settings file.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(240..=4320))]
+    pub height: Option<u32>,
+
+    /// Render backend: auto, vulkan, metal, dx12, gl or none.
+    #[arg(long, value_parser = parse_backend)]
+    pub backend: Option<RenderBackend>,
+
+    /// Log level: error, warn, info, debug or trace.
+    #[arg(long, default_value = "info")]
+    pub log_level: Level,
+
+    /// Log filter in the `tracing` env filter syntax.
+    #[arg(long, default_value = "wgpu=error,naga=warn")]
+    pub log_filter: String,
+
+    /// Show the world inspector at the start, F1 still toggles it.
+    #[arg(long)]
+    pub inspector: bool,
+
+    /// Run without window and GPU.
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Simulation ticks per second of the headless mode.
+    #[arg(long, default_value_t = DEFAULT_TICK_RATE, value_parser = parse_tick_rate, requires = "headless")]
+    pub tick_rate: f64,
+
+    /// Stop the headless run after this amount of ticks.
+    #[arg(long, requires = "headless")]
+    pub ticks: Option<u64>,
+
+    /// Stop the headless run successfully if the condition is reached.
+    #[arg(long, value_enum, requires = "headless")]
+    pub exit_when: Option<HeadlessExit>,
+
+    /// Replay a keyboard recording.
+    #[arg(long, value_name = "FILE", conflicts_with = "record")]
+    pub replay: Option<PathBuf>,
+
+    /// Record the keyboard input into a file if the game exits.
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+}
+
+/// Insert everything the [`Cli`] overrides. Has to be added before the
+/// [`ManagerPlugin`](crate::manager::ManagerPlugin).
+pub struct CliPlugin {
+    pub cli: Cli,
+    /// Loaded recording of `--replay`, read before the app starts so a
+    /// broken file stops the start.
+    pub replay: Option<InputRecording>,
+}
+
+//################################################# Implementations #################################################
+impl Cli {
+    pub fn headless_config(&self) -> Option<HeadlessConfig> {
+        self.headless.then(|| HeadlessConfig {
+            tick_rate: self.tick_rate,
+            max_ticks: self.ticks,
+            exit_condition: self.exit_when,
+        })
+    }
+
+    /// Apply the window overrides. They are not written back to the
+    /// settings file until the settings menu saves.
+    pub fn apply_settings(&self, settings: &mut GameSettings) {
+        if let Some(width) = self.width {
+            settings.graphics.width = width;
+        }
+        if let Some(height) = self.height {
+            settings.graphics.height = height;
+        }
+    }
+}
+
+impl Plugin for CliPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DebugOptions { inspector: self.cli.inspector });
+
+        if let Some(area) = &self.cli.area {
+            app.insert_resource(AreaFile { path: area.clone() });
+        }
+
+        if let Some(spawn) = self.cli.spawn {
+            app.insert_resource(PlayerSpawn(spawn));
+        }
+
+        if let Some(slot) = self.cli.slot {
+            app.add_systems(Startup, move |mut load_event_writer: EventWriter<LoadRequest>| {
+                load_event_writer.send(LoadRequest { slot });
+            });
+        }
+
+        if let Some(recording) = &self.replay {
+            app.add_plugins(InputReplayPlugin { recording: recording.clone() });
+        }
+
+        if let Some(path) = &self.cli.record {
+            app.add_plugins(InputRecordPlugin { path: path.clone() });
+        }
+    }
+}
+
+fn parse_position(value: &str) -> Result<Vec3, String> {
+    let parts = value.split(',')
+        .map(|part| part.trim().parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(|error| format!("{} is not a number", error))?;
+
+    match parts.as_slice() {
+        [x, y, z] => Ok(Vec3::new(*x, *y, *z)),
+        _ => Err(format!("expected three values like `1.0,30.0,1.0`, got {}", parts.len())),
+    }
+}
+
+fn parse_area(value: &str) -> Result<String, String> {
+    let area_file = AreaFile { path: value.to_string() };
+    match area_file.procedural_seed() {
+        Some(Err(error)) => Err(error),
+        _ => Ok(area_file.path),
+    }
+}
+
+fn parse_backend(value: &str) -> Result<RenderBackend, String> {
+    RenderBackend::parse(value)
+        .ok_or_else(|| format!("unknown backend `{}`, use auto, vulkan, metal, dx12, gl or none", value))
+}
+
+fn parse_tick_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(tick_rate) if tick_rate > 0.0 && tick_rate.is_finite() => Ok(tick_rate),
+        _ => Err(format!("`{}` is no positive tick rate", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if the clap definition has no conflicts.
+    #[test]
+    fn test_cli_definition() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+    }
+
+    /// Unit Test for check if all arguments are parsed into the expected values.
+    #[test]
+    fn test_cli_parse_arguments() {
+        let cli = Cli::try_parse_from([
+            "mira-game", "--area", "maps/forest.glb", "--spawn", "-4,12.5,3", "--slot", "2",
+            "--backend", "gl", "--log-level", "debug", "--headless", "--ticks", "600", "--exit-when", "area-ready"
+        ]).unwrap();
+
+        assert_eq!(cli.area.as_deref(), Some("maps/forest.glb"));
+        let cli = Cli::try_parse_from(["mira-game", "--area", "procedural:42"]).unwrap();
+        assert_eq!(cli.area.as_deref(), Some("procedural:42"));
+        assert_eq!(cli.spawn, Some(Vec3::new(-4.0, 12.5, 3.0)));
+        assert_eq!(cli.slot, Some(2));
+        assert_eq!(cli.backend, Some(RenderBackend::Gl));
+        assert_eq!(cli.log_level, Level::DEBUG);
+
+        let config = cli.headless_config().unwrap();
+        assert_eq!(config.tick_rate, 60.0);
+        assert_eq!(config.max_ticks, Some(600));
+        assert_eq!(config.exit_condition, Some(HeadlessExit::AreaReady));
+    }
+
+    /// Unit Test for check if invalid arguments give an usage error.
+    #[test]
+    fn test_cli_invalid_arguments() {
+        assert!(Cli::try_parse_from(["mira-game", "--spawn", "1,2"]).is_err());
+        assert!(Cli::try_parse_from(["mira-game", "--area", "procedural:hills"]).is_err());
+        assert!(Cli::try_parse_from(["mira-game", "--backend", "glide"]).is_err());
+        assert!(Cli::try_parse_from(["mira-game", "--ticks", "10"]).is_err());
+        assert!(Cli::try_parse_from(["mira-game", "--headless", "--tick-rate", "0"]).is_err());
+        assert!(Cli::try_parse_from(["mira-game", "--width", "10"]).is_err());
+        assert!(Cli::try_parse_from(["mira-game", "--unknown"]).is_err());
+    }
+}
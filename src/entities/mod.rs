@@ -1,10 +1,11 @@
 pub mod player;
 
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 use crate::entities::player::PlayerPlugin;
 
 //################################################# Models #################################################
-#[derive(Component, Reflect, Debug)]
+#[derive(Component, Reflect, Debug, Clone, Serialize, Deserialize)]
 #[reflect(Component)]
 pub struct EntitiesBase {
     pub max_health: f32,
@@ -18,7 +19,7 @@ pub struct EntitiesBase {
     pub resistances: Resistances
 }
 
-#[derive(Component, Reflect, Debug)]
+#[derive(Component, Reflect, Debug, Clone, Serialize, Deserialize)]
 #[reflect(Component)]
 pub struct OffsetTransform {
     pub y: f32,
@@ -26,7 +27,7 @@ pub struct OffsetTransform {
     pub z: f32
 }
 
-#[derive(Component, Reflect, Debug)]
+#[derive(Component, Reflect, Debug, Clone, Serialize, Deserialize)]
 #[reflect(Component)]
 pub struct CurrentStats {
     pub health: f32,
@@ -35,7 +36,7 @@ pub struct CurrentStats {
     pub mana: f32,
 }
 
-#[derive(Component, Reflect, Debug)]
+#[derive(Component, Reflect, Debug, Clone, Serialize, Deserialize)]
 #[reflect(Component)]
 pub struct PhysicalDefence {
     pub vs_strike: f32,
@@ -43,7 +44,7 @@ pub struct PhysicalDefence {
     pub vs_thrust: f32
 }
 
-#[derive(Component, Reflect, Debug)]
+#[derive(Component, Reflect, Debug, Clone, Serialize, Deserialize)]
 #[reflect(Component)]
 pub struct GeneralDefence {
     pub physical_defence: PhysicalDefence,
@@ -54,7 +55,7 @@ pub struct GeneralDefence {
     pub corruption_defence: f32,
 }
 
-#[derive(Component, Reflect, Debug)]
+#[derive(Component, Reflect, Debug, Clone, Serialize, Deserialize)]
 #[reflect(Component)]
 pub struct Resistances {
     pub bleed_resistance: f32,
@@ -65,6 +66,14 @@ pub struct Resistances {
     pub rotten_resistance: f32
 }
 
+/// Noise an entity makes for the AI perception. Other entities inside of
+/// the radius can hear it.
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+pub struct NoiseEmitter {
+    pub radius: f32,
+}
+
 #[derive(Component, Reflect, Resource, Debug)]
 pub struct Animations {
     pub(crate) animations: Vec<AnimationNodeIndex>,
@@ -72,6 +81,30 @@ pub struct Animations {
     pub graph: Handle<AnimationGraph>
 }
 
+/// Send if all enemies should go back to their spawn with full stats,
+/// like after a checkpoint rest or the player respawn.
+#[derive(Event, Debug, Default)]
+pub struct ResetEnemies;
+
+/// Send if a boss is defeated. `id` is the unique name of the boss and is
+/// stored in the world flags so the boss stays dead after loading. There
+/// are no boss entities yet, so nothing sends it and the boss kill autosave
+/// has no trigger.
+#[derive(Event, Debug, Clone)]
+#[allow(dead_code)]
+pub struct BossKilled {
+    pub id: String,
+}
+
+impl EntitiesBase {
+    /// Fill health, stamina and mana up to their max values.
+    pub fn restore_stats(&mut self) {
+        self.current_stats.health = self.max_health;
+        self.current_stats.stamina = self.max_stamina;
+        self.current_stats.mana = self.max_mana;
+    }
+}
+
 //################################################# Default Values #################################################
 impl Default for EntitiesBase {
     fn default() -> Self {
@@ -150,6 +183,9 @@ pub struct EntitiesPlugin;
 
 impl Plugin for EntitiesPlugin {
     fn build(&self, app: &mut App) {
+        app.register_type::<NoiseEmitter>();
+        app.add_event::<ResetEnemies>();
+        app.add_event::<BossKilled>();
         app.add_plugins(PlayerPlugin);
     }
 }
\ No newline at end of file
@@ -1,13 +1,28 @@
 mod player_base;
+mod player_climbing;
+mod player_death;
 mod player_input;
+mod player_landing;
+mod player_sneaking;
 
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 use crate::entities::EntitiesBase;
 use crate::entities::player::player_base::PlayerBasePlugin;
+use crate::entities::player::player_climbing::PlayerClimbingPlugin;
+use crate::entities::player::player_death::PlayerDeathPlugin;
 use crate::entities::player::player_input::PlayerInputPlugin;
+use crate::entities::player::player_landing::PlayerLandingPlugin;
+use crate::entities::player::player_sneaking::PlayerSneakingPlugin;
+
+pub use crate::entities::player::player_base::PlayerSpawn;
+pub use crate::entities::player::player_climbing::Climber;
+pub use crate::entities::player::player_death::DeathSequence;
+pub use crate::entities::player::player_landing::FallTracker;
+pub use crate::entities::player::player_sneaking::{Stance, STANDING_HALF_EXTENTS};
 
 //################################################# Models #################################################
-#[derive(Component, Reflect, Resource, Debug)]
+#[derive(Component, Reflect, Resource, Debug, Clone, Serialize, Deserialize)]
 #[reflect(Component)]
 pub struct Player {
     pub general: PlayerGeneralStats,
@@ -20,7 +35,7 @@ pub struct Player {
     pub consume_entries: ConsumeEntries
 }
 
-#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[reflect(Component)]
 pub enum PlayerState {
     Idling,
@@ -36,7 +51,15 @@ pub enum PlayerState {
     Dead,
 }
 
-#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+/// Send every time the player loses health. The amount is already applied
+/// to [`CurrentStats::health`](crate::entities::CurrentStats).
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlayerDamaged {
+    pub entity: Entity,
+    pub amount: f32,
+}
+
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[reflect(Component)]
 pub enum PlayerEnvironmentState {
     Fighting,
@@ -48,7 +71,7 @@ pub enum PlayerEnvironmentState {
     Trapped
 }
 
-#[derive(Component, Reflect, Debug)]
+#[derive(Component, Reflect, Debug, Clone, Serialize, Deserialize)]
 #[reflect(Component)]
 pub struct StatsTimer {
     pub sprint_timer: f32,
@@ -56,7 +79,7 @@ pub struct StatsTimer {
     pub stamina_fill_delay: f32,
 }
 
-#[derive(Component, Reflect, Debug)]
+#[derive(Component, Reflect, Debug, Clone, Serialize, Deserialize)]
 #[reflect(Component)]
 pub struct ConsumeEntries {
     pub dodge: bool,
@@ -64,7 +87,7 @@ pub struct ConsumeEntries {
     pub jump: bool,
 }
 
-#[derive(Component, Reflect, Debug)]
+#[derive(Component, Reflect, Debug, Clone, Serialize, Deserialize)]
 #[reflect(Component)]
 pub struct PlayerSkillAbleStats {
     pub vitality: f32,
@@ -78,7 +101,7 @@ pub struct PlayerSkillAbleStats {
     pub luck: f32,
 }
 
-#[derive(Component, Reflect, Debug)]
+#[derive(Component, Reflect, Debug, Clone, Serialize, Deserialize)]
 #[reflect(Component)]
 pub struct PlayerGeneralStats {
     pub level: u16,
@@ -87,6 +110,18 @@ pub struct PlayerGeneralStats {
     pub discovery: f32
 }
 
+#[derive(Component, Reflect, Debug, Default, Clone, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct Inventory {
+    pub items: Vec<InventoryItem>,
+}
+
+#[derive(Reflect, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InventoryItem {
+    pub id: String,
+    pub count: u32,
+}
+
 //################################################# Default Values #################################################
 impl Default for Player {
     fn default() -> Self {
@@ -109,6 +144,14 @@ impl Default for PlayerState {
     }
 }
 
+impl PlayerState {
+    /// States in which the default movement input is not handled. Climbing
+    /// has his own input handling.
+    pub fn blocks_input(&self) -> bool {
+        matches!(self, PlayerState::Grounded | PlayerState::Climbing | PlayerState::Dead)
+    }
+}
+
 impl Default for PlayerEnvironmentState {
     fn default() -> Self {
         PlayerEnvironmentState::Exploring
@@ -166,7 +209,16 @@ pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.register_type::<Player>();
-        app.add_plugins((PlayerBasePlugin, PlayerInputPlugin));
+        app.register_type::<Player>()
+            .register_type::<Inventory>();
+        app.add_event::<PlayerDamaged>();
+        app.add_plugins((
+            PlayerBasePlugin,
+            PlayerInputPlugin,
+            PlayerLandingPlugin,
+            PlayerClimbingPlugin,
+            PlayerSneakingPlugin,
+            PlayerDeathPlugin
+        ));
     }
 }
\ No newline at end of file
@@ -2,34 +2,61 @@ use bevy::core_pipeline::bloom::BloomSettings;
 use bevy::core_pipeline::experimental::taa::TemporalAntiAliasBundle;
 use bevy::prelude::*;
 use bevy_atmosphere::plugin::{AtmosphereCamera, AtmospherePlugin};
-use bevy_rapier3d::prelude::*;
 use bevy_third_person_camera::*;
-use crate::entities::player::{Player, PlayerSkillAbleStats};
+use crate::entities::NoiseEmitter;
+use crate::entities::player::{Inventory, Player, PlayerSkillAbleStats};
+use crate::entities::player::player_climbing::Climber;
 use crate::entities::player::player_input::Grounded;
-use crate::manager::PlayerSets;
+use crate::entities::player::player_landing::FallTracker;
+use crate::entities::player::player_sneaking::{Stance, STANDING_HALF_EXTENTS};
+use crate::logic::{AssetFailures, GameSettings, LoadingData};
+use crate::manager::{PlayerSets, RunMode};
+use crate::physics::player_body;
+
+/// Default position the player is spawned at.
+pub const PLAYER_SPAWN: Vec3 = Vec3::new(1.0, 30.0, 1.0);
+
+/// World position the player is spawned at, also used as respawn without
+/// checkpoint. Can be changed with the `--spawn` argument.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct PlayerSpawn(pub Vec3);
+
+impl Default for PlayerSpawn {
+    fn default() -> Self {
+        Self(PLAYER_SPAWN)
+    }
+}
 
 pub struct PlayerBasePlugin;
 
 impl Plugin for PlayerBasePlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((ThirdPersonCameraPlugin, AtmospherePlugin));
+        app.init_resource::<PlayerSpawn>();
+
+        app.add_systems(Startup, load_player_model.in_set(PlayerSets));
+        app.add_systems(Update, replace_failed_player_model.run_if(resource_changed::<AssetFailures>));
 
-        app.add_systems(Startup, (load_player_model, load_player_camera).in_set(PlayerSets));
+        // The headless mode has no window, so no camera is needed.
+        if RunMode::of(app) == RunMode::Windowed {
+            app.add_plugins((ThirdPersonCameraPlugin, AtmospherePlugin));
+            app.add_systems(Startup, load_player_camera.in_set(PlayerSets));
+        }
     }
 }
 
-fn load_player_model(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn load_player_model(mut commands: Commands,
+                     asset_server: Res<AssetServer>,
+                     spawn: Res<PlayerSpawn>,
+                     mut loading_data: ResMut<LoadingData>
+) {
+    let player_scene = asset_server.load(GltfAssetLabel::Scene(0).from_asset("entities/player.glb"));
+    loading_data.register(player_scene.clone());
+
     commands.spawn((
         Name::new("Player"),
-/*        PbrBundle {
-            mesh: meshes.add(Cuboid::from_size(Vec3::splat(1.0))),
-            material: materials.add(Color::srgb_u8(200, 0, 0)),
-            transform: Transform::from_xyz(0.0, 2.0, 0.0),
-            ..default()
-        },*/
         SceneBundle {
-            scene: asset_server.load(GltfAssetLabel::Scene(0).from_asset("entities/player.glb")),
-            transform: Transform::from_xyz(1.0, 30.0, 1.0),
+            scene: player_scene,
+            transform: Transform::from_translation(spawn.0),
             ..default()
         },
         Player {
@@ -37,21 +64,50 @@ fn load_player_model(mut commands: Commands, asset_server: Res<AssetServer>) {
             ..default()
         },
         PlayerSkillAbleStats::default(),
+        Inventory::default(),
         ThirdPersonCameraTarget,
-        RigidBody::Dynamic,
-        Collider::cuboid(0.5, 0.5, 0.5),
-        Velocity::default(),
-        Grounded(true),
-        Damping {
-            linear_damping: 0.2,
-            angular_damping: 0.5, // stop random rotating.
-        },
-        LockedAxes::ROTATION_LOCKED_X | LockedAxes::ROTATION_LOCKED_Z,
+        player_body(STANDING_HALF_EXTENTS),
+        Grounded::default(),
+        FallTracker::default(),
+        Climber::default(),
+        Stance::default(),
+        NoiseEmitter::default(),
     ));
 }
 
-fn load_player_camera(mut commands: Commands) {
-    commands.spawn((
+/// Replace the player scene with a red capsule if `player.glb` failed, so the
+/// game stays playable without the model.
+fn replace_failed_player_model(mut commands: Commands,
+                               asset_server: Res<AssetServer>,
+                               asset_failures: Res<AssetFailures>,
+                               mut meshes: ResMut<Assets<Mesh>>,
+                               mut materials: ResMut<Assets<StandardMaterial>>,
+                               player_query: Query<(Entity, &Handle<Scene>), With<Player>>
+) {
+    for (entity, scene) in player_query.iter() {
+        if !asset_failures.is_failed(&asset_server, scene) {
+            continue;
+        }
+
+        warn!("Player model failed, use the fallback model");
+        commands.entity(entity)
+            .remove::<Handle<Scene>>()
+            .insert((
+                meshes.add(fallback_player_shape(STANDING_HALF_EXTENTS)),
+                materials.add(Color::srgb_u8(200, 0, 0)),
+            ));
+    }
+}
+
+/// Capsule which fills the player collider of `half_extents`, as wide as
+/// the narrow side and as high as the collider.
+fn fallback_player_shape(half_extents: Vec3) -> Capsule3d {
+    let radius = half_extents.x.min(half_extents.z);
+    Capsule3d::new(radius, ((half_extents.y - radius) * 2.0).max(0.0))
+}
+
+fn load_player_camera(mut commands: Commands, settings: Res<GameSettings>) {
+    let mut camera = commands.spawn((
         Name::new("PlayerCamera"),
         Camera3dBundle {
             transform: Transform::from_xyz(-7.1, 6.8, 22.2).looking_at(Vec3::ZERO, Vec3::Y),
@@ -59,28 +115,34 @@ fn load_player_camera(mut commands: Commands) {
                 hdr: true,
                 ..default()
             },
+            projection: Projection::Perspective(PerspectiveProjection {
+                fov: settings.graphics.fov.to_radians(),
+                ..default()
+            }),
             ..default()
         },
         ThirdPersonCamera {
-            sensitivity: Vec2::new(2.8, 2.8),
-            zoom: Zoom::new(4.5, 30.0),
+            sensitivity: settings.controls.sensitivity(),
+            zoom: settings.controls.zoom(),
             cursor_lock_key: KeyCode::Escape,
             offset: Offset::new(0.0, 0.8,),
             offset_enabled: true,
             ..default()
         },
-        TemporalAntiAliasBundle::default(),
-        BloomSettings::default(),
         AtmosphereCamera::default(),
         FogSettings {
             color: Color::srgb(0.25, 0.25, 0.30),
-            falloff: FogFalloff::Linear {
-                start: 325.0,
-                end: 500.0,
-            },
+            falloff: settings.graphics.fog_falloff(),
             ..default()
         },
     ));
+
+    if settings.graphics.taa {
+        camera.insert(TemporalAntiAliasBundle::default());
+    }
+    if settings.graphics.bloom {
+        camera.insert(BloomSettings::default());
+    }
 }
 
 #[cfg(test)]
@@ -93,6 +155,7 @@ mod tests {
     fn test_load_player_camera() {
         let mut app = App::new();
         app.add_plugins(MinimalPlugins);
+        app.init_resource::<GameSettings>();
 
         app.add_systems(Startup, load_player_camera);
         app.update();
@@ -116,4 +179,16 @@ mod tests {
             panic!("Unexpected FogFalloff type");
         }
     }
+
+    /// Unit Test for check if the fallback capsule has the height and width of the player collider.
+    #[test]
+    fn test_fallback_player_shape() {
+        let capsule = fallback_player_shape(Vec3::new(0.25, 1.0, 0.5));
+        assert_eq!(capsule.radius, 0.25);
+        assert_eq!(capsule.half_length * 2.0 + capsule.radius * 2.0, 2.0);
+
+        let capsule = fallback_player_shape(STANDING_HALF_EXTENTS);
+        assert_eq!(capsule.radius, STANDING_HALF_EXTENTS.x);
+        assert_eq!(capsule.half_length, 0.0);
+    }
 }
\ No newline at end of file
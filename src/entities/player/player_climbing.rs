@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use crate::entities::player::{Player, PlayerDamaged, PlayerState};
+use crate::environment::Climbable;
+use crate::manager::PlayerSets;
+use crate::physics::{set_body_kinematic, BodyVelocity};
+
+/// Climb speed in meters per second.
+const CLIMB_SPEED: f32 = 2.0;
+/// Speed in meters per second of the slide down action.
+const SLIDE_SPEED: f32 = 7.0;
+/// Distance from the player center to his feet, used for the dismount positions.
+const FEET_OFFSET: f32 = 0.5;
+/// Distance the player is placed away from the volume at the top dismount.
+const TOP_DISMOUNT_DISTANCE: f32 = 0.8;
+/// Push away from the volume if the player jumps off.
+const JUMP_OFF_SPEED: f32 = 3.0;
+/// Upward part of the push if the player jumps off.
+const JUMP_OFF_UP_SPEED: f32 = 2.0;
+
+/// Climbing data of the player. `volume` is the [`Climbable`] entity while
+/// the player is in [`PlayerState::Climbing`].
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+pub struct Climber {
+    pub volume: Option<Entity>,
+    pub sliding: bool,
+}
+
+pub struct PlayerClimbingPlugin;
+
+impl Plugin for PlayerClimbingPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Climber>();
+
+        app.add_systems(Update, (
+            mount_climbable,
+            update_climbing,
+            cancel_climbing_on_damage
+        ).chain().in_set(PlayerSets));
+    }
+}
+
+fn mount_climbable(mut commands: Commands,
+                   keyboard: Res<ButtonInput<KeyCode>>,
+                   climbable_query: Query<(Entity, &GlobalTransform), With<Climbable>>,
+                   mut player_query: Query<(Entity, &Transform, &mut Player, &mut Climber, BodyVelocity)>
+) {
+    // Shared with the checkpoint rest, which runs first and takes the press near a checkpoint.
+    let interact_key = KeyCode::KeyE;
+    if !keyboard.just_pressed(interact_key) {
+        return;
+    }
+
+    for (entity, transform, mut player, mut climber, mut velocity) in player_query.iter_mut() {
+        if player.state.blocks_input() || player.state == PlayerState::Dodging {
+            continue;
+        }
+
+        let volume = climbable_query.iter()
+            .find(|(_, volume_transform)| Climbable::contains(volume_transform, transform.translation));
+
+        if let Some((volume_entity, _)) = volume {
+            set_body_kinematic(&mut commands, entity, true);
+            velocity.set_linvel(Vec3::ZERO);
+            velocity.set_angvel(Vec3::ZERO);
+            player.state = PlayerState::Climbing;
+            climber.volume = Some(volume_entity);
+            climber.sliding = false;
+        }
+    }
+}
+
+fn update_climbing(mut commands: Commands,
+                   time: Res<Time>,
+                   keyboard: Res<ButtonInput<KeyCode>>,
+                   climbable_query: Query<&GlobalTransform, With<Climbable>>,
+                   mut player_query: Query<(Entity, &mut Transform, &mut Player, &mut Climber, BodyVelocity)>
+) {
+    let up_key = KeyCode::KeyW;
+    let down_key = KeyCode::KeyS;
+    let slide_key = KeyCode::Space;
+    let jump_key = KeyCode::KeyF;
+
+    for (entity, mut transform, mut player, mut climber, mut velocity) in player_query.iter_mut() {
+        if player.state != PlayerState::Climbing {
+            continue;
+        }
+
+        let Some(volume_transform) = climber.volume.and_then(|volume| climbable_query.get(volume).ok()) else {
+            release_climbable(&mut commands, entity, &mut player, &mut climber);
+            continue;
+        };
+        let (top, bottom) = Climbable::top_and_bottom(volume_transform);
+
+        if keyboard.just_pressed(jump_key) {
+            release_climbable(&mut commands, entity, &mut player, &mut climber);
+            velocity.set_linvel(jump_off_velocity(volume_transform.translation(), &transform));
+            continue;
+        }
+
+        climber.sliding = keyboard.pressed(slide_key);
+        let mut vertical_speed = 0.0;
+        if climber.sliding {
+            vertical_speed = -SLIDE_SPEED;
+        } else {
+            if keyboard.pressed(up_key) {
+                vertical_speed += CLIMB_SPEED;
+            }
+            if keyboard.pressed(down_key) {
+                vertical_speed -= CLIMB_SPEED;
+            }
+        }
+
+        transform.translation.y += vertical_speed * time.delta_seconds();
+
+        if transform.translation.y - FEET_OFFSET >= top.y {
+            let away = (transform.translation - volume_transform.translation()).with_y(0.0).normalize_or_zero();
+            transform.translation = top + Vec3::Y * FEET_OFFSET - away * TOP_DISMOUNT_DISTANCE;
+            release_climbable(&mut commands, entity, &mut player, &mut climber);
+        } else if vertical_speed < 0.0 && transform.translation.y - FEET_OFFSET <= bottom.y {
+            transform.translation.y = bottom.y + FEET_OFFSET;
+            release_climbable(&mut commands, entity, &mut player, &mut climber);
+        }
+    }
+}
+
+fn cancel_climbing_on_damage(mut commands: Commands,
+                             mut damaged_event_reader: EventReader<PlayerDamaged>,
+                             mut player_query: Query<(&mut Player, &mut Climber)>
+) {
+    for event in damaged_event_reader.read() {
+        if let Ok((mut player, mut climber)) = player_query.get_mut(event.entity) {
+            if climber.volume.is_some() {
+                release_climbable(&mut commands, event.entity, &mut player, &mut climber);
+            }
+        }
+    }
+}
+
+/// Push of the jump off, from the volume center towards the player plus the
+/// upward part. The facing of the player does not matter, only if he stands
+/// at the volume center he is pushed backwards.
+fn jump_off_velocity(volume_center: Vec3, transform: &Transform) -> Vec3 {
+    let away = (transform.translation - volume_center).xz().normalize_or_zero();
+    let away = if away == Vec2::ZERO { transform.back().xz() } else { away };
+
+    Vec3::new(away.x, 0.0, away.y) * JUMP_OFF_SPEED + Vec3::Y * JUMP_OFF_UP_SPEED
+}
+
+/// Give the player back to the physics simulation. Keeps [`PlayerState::Dead`]
+/// if the damage was lethal.
+fn release_climbable(commands: &mut Commands, entity: Entity, player: &mut Player, climber: &mut Climber) {
+    set_body_kinematic(commands, entity, false);
+    climber.volume = None;
+    climber.sliding = false;
+    if player.state == PlayerState::Climbing {
+        player.state = PlayerState::Idling;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if the jump off pushes away from the volume, even
+    /// if the player faces the volume at an angle.
+    #[test]
+    fn test_jump_off_away_from_volume() {
+        let volume_center = Vec3::new(0.0, 2.0, 0.0);
+        let transform = Transform::from_xyz(1.0, 1.5, 0.0)
+            .looking_at(Vec3::new(0.0, 1.5, 0.6), Vec3::Y);
+
+        let velocity = jump_off_velocity(volume_center, &transform);
+
+        assert!((velocity.xz() - Vec2::X * JUMP_OFF_SPEED).length() < 1e-5);
+        assert_eq!(velocity.y, JUMP_OFF_UP_SPEED);
+    }
+}
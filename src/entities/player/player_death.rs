@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+use crate::entities::ResetEnemies;
+use crate::entities::player::{Player, PlayerState};
+use crate::entities::player::player_base::PlayerSpawn;
+use crate::entities::player::player_landing::FallTracker;
+use crate::environment::{ChunkManager, FloatingOrigin, LastCheckpoint};
+use crate::manager::PlayerSets;
+use crate::physics::BodyVelocity;
+
+/// Time in seconds between death and respawn, used for the death screen and animation.
+const DEATH_SEQUENCE_TIME: f32 = 3.0;
+/// Time in seconds the respawn waits for the chunks after the death sequence,
+/// then the player respawns anyway.
+const RESPAWN_CHUNK_TIMEOUT: f32 = 10.0;
+
+/// Added to the player on death and removed after the respawn.
+#[derive(Component, Reflect, Debug)]
+#[reflect(Component)]
+pub struct DeathSequence {
+    /// Time until the respawn, it gets negative while the respawn waits for the chunks.
+    pub timer: f32,
+    /// World position, see [`FloatingOrigin::to_local`].
+    pub respawn_position: Vec3,
+}
+
+pub struct PlayerDeathPlugin;
+
+impl Plugin for PlayerDeathPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DeathSequence>();
+
+        app.add_systems(Update, (detect_player_death, update_death_sequence).chain().in_set(PlayerSets));
+    }
+}
+
+fn detect_player_death(mut commands: Commands,
+                       last_checkpoint: Res<LastCheckpoint>,
+                       spawn: Res<PlayerSpawn>,
+                       mut chunk_manager: ResMut<ChunkManager>,
+                       mut player_query: Query<(Entity, &mut Player, BodyVelocity), Without<DeathSequence>>
+) {
+    for (entity, mut player, mut velocity) in player_query.iter_mut() {
+        if player.base.current_stats.health > 0.0 {
+            continue;
+        }
+
+        let respawn_position = last_checkpoint.position.unwrap_or(spawn.0);
+        chunk_manager.preload(respawn_position);
+
+        player.base.current_stats.health = 0.0;
+        player.state = PlayerState::Dead;
+        velocity.set_linvel(Vec3::new(0.0, velocity.linvel().y, 0.0));
+        velocity.set_angvel(Vec3::ZERO);
+
+        commands.entity(entity).insert(DeathSequence {
+            timer: DEATH_SEQUENCE_TIME,
+            respawn_position,
+        });
+        info!("Player died, respawn at {:?}", respawn_position);
+    }
+}
+
+/// Wait for the death sequence and the chunks around the respawn position,
+/// then teleport the player and restore his stats. Chunks which are not ready
+/// after [`RESPAWN_CHUNK_TIMEOUT`] do not hold the player dead any longer.
+fn update_death_sequence(mut commands: Commands,
+                         time: Res<Time>,
+                         origin: Res<FloatingOrigin>,
+                         mut chunk_manager: ResMut<ChunkManager>,
+                         mut reset_event_writer: EventWriter<ResetEnemies>,
+                         mut player_query: Query<(Entity, &mut DeathSequence, &mut Transform, &mut Player, &mut FallTracker, BodyVelocity)>
+) {
+    for (entity, mut sequence, mut transform, mut player, mut tracker, mut velocity) in player_query.iter_mut() {
+        sequence.timer -= time.delta_seconds();
+        if sequence.timer > 0.0 {
+            continue;
+        }
+
+        if !chunk_manager.is_area_ready(sequence.respawn_position) {
+            if sequence.timer > -RESPAWN_CHUNK_TIMEOUT {
+                continue;
+            }
+            warn!("Chunks at {:?} are not ready after {}s, respawn anyway", sequence.respawn_position, RESPAWN_CHUNK_TIMEOUT);
+        }
+
+        transform.translation = origin.to_local(sequence.respawn_position);
+        velocity.set_linvel(Vec3::ZERO);
+        velocity.set_angvel(Vec3::ZERO);
+
+        player.base.restore_stats();
+        player.state = PlayerState::Idling;
+        tracker.airborne = false;
+        tracker.protected = true;
+
+        chunk_manager.release(sequence.respawn_position);
+        commands.entity(entity).remove::<DeathSequence>();
+        reset_event_writer.send(ResetEnemies);
+        info!("Player respawned at {:?}", sequence.respawn_position);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use bevy::time::TimeUpdateStrategy;
+    use super::*;
+    use crate::physics::player_body;
+
+    /// Frame time of the tests, below the max delta of the virtual time.
+    const FRAME_TIME: f32 = 0.2;
+
+    fn test_app(checkpoint: Option<Vec3>) -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(FRAME_TIME)));
+        app.add_event::<ResetEnemies>();
+        app.insert_resource(LastCheckpoint { id: checkpoint.map(|_| "checkpoint_start".to_string()), position: checkpoint })
+            .insert_resource(PlayerSpawn(Vec3::new(0.0, 5.0, 0.0)))
+            .init_resource::<ChunkManager>()
+            .init_resource::<FloatingOrigin>();
+        app.add_systems(Update, (detect_player_death, update_death_sequence).chain());
+
+        let mut player = Player::default();
+        player.base.current_stats.health = 0.0;
+        let entity = app.world_mut().spawn((
+            Transform::from_xyz(300.0, -50.0, 0.0),
+            player,
+            FallTracker::default(),
+            player_body(Vec3::splat(0.5)),
+        )).id();
+
+        (app, entity)
+    }
+
+    fn update_for(app: &mut App, seconds: f32) {
+        for _ in 0..(seconds / FRAME_TIME).ceil() as usize {
+            app.update();
+        }
+    }
+
+    /// Unit Test for check if a dead player preloads the last checkpoint and
+    /// respawns there once his chunks are ready, then the preload is released.
+    #[test]
+    fn test_respawn_at_checkpoint() {
+        let checkpoint = Vec3::new(40.0, 2.0, -12.0);
+        let (mut app, player) = test_app(Some(checkpoint));
+        // The fallback ground counts as ready area.
+        app.world_mut().resource_mut::<ChunkManager>().fallback_ground = Some(Entity::PLACEHOLDER);
+
+        app.update();
+        let world = app.world();
+        assert_eq!(world.get::<Player>(player).unwrap().state, PlayerState::Dead);
+        assert_eq!(world.get::<DeathSequence>(player).unwrap().respawn_position, checkpoint);
+        assert_eq!(world.resource::<ChunkManager>().preload_positions, vec![checkpoint]);
+
+        update_for(&mut app, DEATH_SEQUENCE_TIME + FRAME_TIME);
+        let world = app.world();
+        assert!(world.get::<DeathSequence>(player).is_none());
+        assert_eq!(world.get::<Transform>(player).unwrap().translation, checkpoint);
+        let respawned = world.get::<Player>(player).unwrap();
+        assert_eq!(respawned.state, PlayerState::Idling);
+        assert_eq!(respawned.base.current_stats.health, respawned.base.max_health);
+        assert!(world.resource::<ChunkManager>().preload_positions.is_empty());
+        assert_eq!(world.resource::<Events<ResetEnemies>>().len(), 1);
+    }
+
+    /// Unit Test for check if the player respawns at the spawn without
+    /// checkpoint and the respawn stops waiting for chunks after the timeout.
+    #[test]
+    fn test_respawn_at_spawn_after_timeout() {
+        let (mut app, player) = test_app(None);
+
+        update_for(&mut app, DEATH_SEQUENCE_TIME + 1.0);
+        let world = app.world();
+        assert_eq!(world.get::<DeathSequence>(player).unwrap().respawn_position, Vec3::new(0.0, 5.0, 0.0));
+        assert_eq!(world.get::<Player>(player).unwrap().state, PlayerState::Dead);
+
+        update_for(&mut app, RESPAWN_CHUNK_TIMEOUT);
+        let world = app.world();
+        assert!(world.get::<DeathSequence>(player).is_none());
+        assert_eq!(world.get::<Transform>(player).unwrap().translation, Vec3::new(0.0, 5.0, 0.0));
+        assert!(world.resource::<ChunkManager>().preload_positions.is_empty());
+    }
+}
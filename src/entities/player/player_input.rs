@@ -1,8 +1,20 @@
 use bevy::prelude::*;
-use bevy_rapier3d::dynamics::Velocity;
-use bevy_rapier3d::pipeline::QueryFilter;
-use bevy_rapier3d::plugin::RapierContext;
 use crate::entities::player::{Player, PlayerState};
+use crate::entities::player::player_sneaking::Stance;
+use crate::manager::PlayerSets;
+use crate::physics::{BodyVelocity, BodyVelocityReadOnly, PhysicsQuery, ShapeHit};
+
+/// Radius of the sphere which is cast down from the player center. A bit smaller than
+/// the player box so edges are still detected while walls are not.
+const GROUND_PROBE_RADIUS: f32 = 0.45;
+/// Distance below the player box which still counts as ground.
+const GROUND_PROBE_TOLERANCE: f32 = 0.15;
+/// Steepest slope in degrees which counts as ground.
+const MAX_WALKABLE_SLOPE: f32 = 50.0;
+/// Time in seconds the player can still jump after leaving the ground.
+const COYOTE_TIME: f32 = 0.15;
+/// Time in seconds a jump input is remembered before landing.
+const JUMP_BUFFER_TIME: f32 = 0.15;
 
 #[derive(Event)]
 pub enum InputAction {
@@ -11,10 +23,76 @@ pub enum InputAction {
     Sprinting(Vec3),
     Dodge,
     Jump,
+    Sneak,
 }
 
+/// Ground information of the player. Updated every frame by [`ground_check`].
 #[derive(Component, Reflect, Debug)]
-pub struct Grounded(pub bool);
+pub struct Grounded {
+    pub on_ground: bool,
+    pub normal: Vec3,
+    pub slope_angle: f32,
+    pub surface: Option<Entity>,
+    pub coyote_timer: f32,
+    pub jump_buffer_timer: f32,
+}
+
+impl Default for Grounded {
+    fn default() -> Self {
+        Self {
+            on_ground: false,
+            normal: Vec3::Y,
+            slope_angle: 0.0,
+            surface: None,
+            coyote_timer: COYOTE_TIME,
+            jump_buffer_timer: 0.0,
+        }
+    }
+}
+
+impl Grounded {
+    /// Apply the result of the ground probe and advance the coyote and jump buffer timers.
+    pub fn update(&mut self, hit: Option<ShapeHit>, delta: f32) {
+        if let Some(hit) = hit {
+            self.normal = hit.normal;
+            self.slope_angle = slope_angle(hit.normal);
+            self.surface = Some(hit.entity);
+            self.on_ground = self.slope_angle <= MAX_WALKABLE_SLOPE;
+        } else {
+            self.normal = Vec3::Y;
+            self.slope_angle = 0.0;
+            self.surface = None;
+            self.on_ground = false;
+        }
+
+        if self.on_ground {
+            self.coyote_timer = 0.0;
+        } else {
+            self.coyote_timer += delta;
+        }
+        self.jump_buffer_timer = (self.jump_buffer_timer - delta).max(0.0);
+    }
+
+    /// True if the player stands on the ground or left it during the coyote time.
+    pub fn can_jump(&self) -> bool {
+        self.on_ground || self.coyote_timer < COYOTE_TIME
+    }
+
+    pub fn buffer_jump(&mut self) {
+        self.jump_buffer_timer = JUMP_BUFFER_TIME;
+    }
+
+    pub fn jump_buffered(&self) -> bool {
+        self.jump_buffer_timer > 0.0
+    }
+
+    /// Clear the buffered input and the coyote time so a jump is only executed once.
+    pub fn consume_jump(&mut self) {
+        self.on_ground = false;
+        self.coyote_timer = COYOTE_TIME;
+        self.jump_buffer_timer = 0.0;
+    }
+}
 
 pub struct PlayerInputPlugin;
 
@@ -27,114 +105,135 @@ impl Plugin for PlayerInputPlugin {
             fetch_keyboard_input,
             update_movement,
             ground_check
-        ));
+        ).in_set(PlayerSets));
     }
 }
 
 fn fetch_keyboard_input(mut input_event_writer: EventWriter<InputAction>,
                         keyboard: Res<ButtonInput<KeyCode>>,
                         camera_query: Query<&Transform, (With<Camera>, Without<Player>)>,
-                        mut player_query: Query<(&mut Player, &Grounded), With<Player>>,
+                        mut player_query: Query<(&mut Player, &mut Grounded), With<Player>>,
                         time: Res<Time>,
 ) {
-    for (mut player, grounded) in player_query.iter_mut() {
-        if let Ok(cam_transform) = camera_query.get_single() {
-            let forward_key = KeyCode::KeyW;
-            let backward_key = KeyCode::KeyS;
-            let left_key = KeyCode::KeyA;
-            let right_key = KeyCode::KeyD;
-
-            let sprint_key = KeyCode::Space;
-            let dodge_key = KeyCode::Space;
-            let jump_key = KeyCode::KeyF;
-
-            let mut dodge_alone = true;
-            if dodge_key == sprint_key {
-                dodge_alone = false;
-            }
+    for (mut player, mut grounded) in player_query.iter_mut() {
+        if player.state.blocks_input() {
+            continue;
+        }
 
-            let mut direction = Vec3::ZERO;
-            if keyboard.pressed(forward_key) {
-                direction += Vec3::new(cam_transform.forward().x, direction.y, cam_transform.forward().z);
-            }
+        // Without camera, like in the headless mode, the world axes are used.
+        let cam_transform = camera_query.get_single().copied().unwrap_or_default();
 
-            if keyboard.pressed(backward_key) {
-                direction += Vec3::new(cam_transform.back().x, direction.y, cam_transform.back().z);
-            }
+        let forward_key = KeyCode::KeyW;
+        let backward_key = KeyCode::KeyS;
+        let left_key = KeyCode::KeyA;
+        let right_key = KeyCode::KeyD;
 
-            if keyboard.pressed(left_key) {
-                direction += cam_transform.left().as_vec3();
-            }
+        let sprint_key = KeyCode::Space;
+        let dodge_key = KeyCode::Space;
+        let jump_key = KeyCode::KeyF;
+        let sneak_key = KeyCode::KeyC;
 
-            if keyboard.pressed(right_key) {
-                direction += cam_transform.right().as_vec3();
-            }
+        let mut dodge_alone = true;
+        if dodge_key == sprint_key {
+            dodge_alone = false;
+        }
 
-            if direction.length_squared() > 0.0 {
-                let normalized_direction = direction.normalize();
-                if keyboard.pressed(forward_key) || keyboard.pressed(backward_key) || keyboard.pressed(left_key) || keyboard.pressed(right_key) {
-                    if player.state != PlayerState::Dodging && player.state != PlayerState::Jumping {
-                        input_event_writer.send(InputAction::Move(normalized_direction));
-                    }
-                } else {
-                    if player.state != PlayerState::Dodging && player.state != PlayerState::Jumping {
-                        input_event_writer.send(InputAction::Idle);
-                    }
+        let mut direction = Vec3::ZERO;
+        if keyboard.pressed(forward_key) {
+            direction += Vec3::new(cam_transform.forward().x, direction.y, cam_transform.forward().z);
+        }
+
+        if keyboard.pressed(backward_key) {
+            direction += Vec3::new(cam_transform.back().x, direction.y, cam_transform.back().z);
+        }
+
+        if keyboard.pressed(left_key) {
+            direction += cam_transform.left().as_vec3();
+        }
+
+        if keyboard.pressed(right_key) {
+            direction += cam_transform.right().as_vec3();
+        }
+
+        if direction.length_squared() > 0.0 {
+            let normalized_direction = direction.normalize();
+            if keyboard.pressed(forward_key) || keyboard.pressed(backward_key) || keyboard.pressed(left_key) || keyboard.pressed(right_key) {
+                if player.state != PlayerState::Dodging && player.state != PlayerState::Jumping {
+                    input_event_writer.send(InputAction::Move(normalized_direction));
                 }
             } else {
                 if player.state != PlayerState::Dodging && player.state != PlayerState::Jumping {
                     input_event_writer.send(InputAction::Idle);
                 }
             }
+        } else {
+            if player.state != PlayerState::Dodging && player.state != PlayerState::Jumping {
+                input_event_writer.send(InputAction::Idle);
+            }
+        }
 
-            if keyboard.just_pressed(sprint_key) {
-                if player.state == PlayerState::Dodging {
-                    return;
-                }
-                player.timers.sprint_timer = 0.0;
-            } else if keyboard.pressed(sprint_key) {
-                player.timers.sprint_timer += time.delta_seconds();
-
-                if player.timers.sprint_timer > 0.6 {
-                    input_event_writer.send(InputAction::Sprinting(direction.normalize()));
-                }
-            } else if keyboard.just_released(sprint_key) {
-                if player.timers.sprint_timer <= 0.2 && !dodge_alone {
-                    input_event_writer.send(InputAction::Dodge);
-                }
-                player.timers.sprint_timer = 0.0;
+        if keyboard.just_pressed(sprint_key) {
+            if player.state == PlayerState::Dodging {
+                return;
             }
+            player.timers.sprint_timer = 0.0;
+        } else if keyboard.pressed(sprint_key) {
+            player.timers.sprint_timer += time.delta_seconds();
 
-            if dodge_alone {
-                if keyboard.just_pressed(dodge_key) {
-                    input_event_writer.send(InputAction::Dodge);
-                    player.timers.sprint_timer = 0.0;
-                }
+            if player.timers.sprint_timer > 0.6 {
+                input_event_writer.send(InputAction::Sprinting(direction.normalize()));
+            }
+        } else if keyboard.just_released(sprint_key) {
+            if player.timers.sprint_timer <= 0.2 && !dodge_alone {
+                input_event_writer.send(InputAction::Dodge);
             }
+            player.timers.sprint_timer = 0.0;
+        }
 
-            if keyboard.just_pressed(jump_key) && player.state != PlayerState::Jumping && grounded.0 {
-                input_event_writer.send(InputAction::Jump);
+        if dodge_alone {
+            if keyboard.just_pressed(dodge_key) {
+                input_event_writer.send(InputAction::Dodge);
+                player.timers.sprint_timer = 0.0;
             }
         }
+
+        if keyboard.just_pressed(sneak_key) && player.state != PlayerState::Dodging {
+            input_event_writer.send(InputAction::Sneak);
+        }
+
+        if keyboard.just_pressed(jump_key) {
+            grounded.buffer_jump();
+        }
+
+        if grounded.jump_buffered() && grounded.can_jump() && player.state != PlayerState::Jumping {
+            input_event_writer.send(InputAction::Jump);
+        }
     }
 }
 
 fn update_movement(time: Res<Time>,
                    mut input_event_reader: EventReader<InputAction>,
-                   mut player_query: Query<(&mut Transform, &mut Velocity, &mut Player, &mut Grounded)>
+                   mut player_query: Query<(&mut Transform, BodyVelocity, &mut Player, &mut Grounded, &Stance)>
 ) {
     for event in input_event_reader.read() {
-        for (mut transform, mut velocity, mut player, mut grounded) in player_query.iter_mut() {
+        for (mut transform, mut velocity, mut player, mut grounded, stance) in player_query.iter_mut() {
+            if player.state.blocks_input() {
+                continue;
+            }
+
             match event {
                 InputAction::Move(direction) => {
                     if direction.length_squared() > 0.0 {
                         let flat_direction = Vec3::new(direction.x, 0.0, direction.z).normalize();
                         let target_rotation = Quat::from_rotation_arc(-Vec3::Z, flat_direction);
                         transform.rotation = transform.rotation.slerp(target_rotation, 0.1);
-                        let movement_speed = (player.base.speed * 100.0) * time.delta_seconds();
-                        velocity.linvel = Vec3::new(flat_direction.x * movement_speed, velocity.linvel.y, flat_direction.z * movement_speed);
-                        player.state = PlayerState::Moving;
-                        velocity.angvel = Vec3::ZERO;
+                        let mut movement_speed = (player.base.speed * 100.0) * time.delta_seconds();
+                        if stance.crouched {
+                            movement_speed *= player.speed_sneaking_multiplier;
+                        }
+                        velocity.set_linvel(Vec3::new(flat_direction.x * movement_speed, velocity.linvel().y, flat_direction.z * movement_speed));
+                        player.state = if stance.crouched { PlayerState::Sneaking } else { PlayerState::Moving };
+                        velocity.set_angvel(Vec3::ZERO);
                     }
                 }
 
@@ -153,17 +252,23 @@ fn update_movement(time: Res<Time>,
                             transform.rotation = transform.rotation.slerp(target_rotation, 0.1);
                         }
 
-                        let movement_speed = ((player.base.speed * player.speed_sprinting_multiplier) * 100.0) * time.delta_seconds();
-                        velocity.linvel = Vec3::new(flat_direction.x * movement_speed, velocity.linvel.y, flat_direction.z * movement_speed);
+                        // Sprint stands the player up, until then he keeps sneaking.
+                        let (multiplier, state) = if stance.crouched {
+                            (player.speed_sneaking_multiplier, PlayerState::Sneaking)
+                        } else {
+                            (player.speed_sprinting_multiplier, PlayerState::Sprinting)
+                        };
+                        let movement_speed = ((player.base.speed * multiplier) * 100.0) * time.delta_seconds();
+                        velocity.set_linvel(Vec3::new(flat_direction.x * movement_speed, velocity.linvel().y, flat_direction.z * movement_speed));
                         if player.state != PlayerState::Jumping {
-                            player.state = PlayerState::Sprinting;
+                            player.state = state;
                         }
-                        velocity.angvel = Vec3::ZERO;
+                        velocity.set_angvel(Vec3::ZERO);
                     }
                 }
 
                 InputAction::Dodge => {
-                    if !grounded.0 {
+                    if !grounded.on_ground {
                         return;
                     }
                     if player.base.current_stats.stamina < 1.0 {
@@ -174,48 +279,116 @@ fn update_movement(time: Res<Time>,
                     let dodge_direction = transform.forward().as_vec3().normalize();
                     let dodge_speed = dodge_distance / dodge_duration;
 
-                    velocity.linvel = Vec3::new(dodge_direction.x * dodge_speed, velocity.linvel.y, dodge_direction.z * dodge_speed);
+                    velocity.set_linvel(Vec3::new(dodge_direction.x * dodge_speed, velocity.linvel().y, dodge_direction.z * dodge_speed));
                     player.state = PlayerState::Dodging;
-                    velocity.angvel = Vec3::ZERO;
+                    velocity.set_angvel(Vec3::ZERO);
                 }
 
                 InputAction::Jump => {
-                    if player.base.current_stats.stamina < 1.0 {
+                    if player.base.current_stats.stamina < 1.0 || stance.crouched {
                         return;
                     }
 
-                    if grounded.0 && player.state != PlayerState::Dodging {
-                        velocity.linvel.y = player.base.jump_height * 1.4;
+                    if grounded.can_jump() && player.state != PlayerState::Dodging {
+                        velocity.set_linvel(velocity.linvel().with_y(player.base.jump_height * 1.4));
                         player.state = PlayerState::Jumping;
-                        grounded.0 = false;
+                        grounded.consume_jump();
                     }
                 }
 
+                InputAction::Sneak => {}
+
                 InputAction::Idle => {
-                    player.state = PlayerState::Idling;
-                    velocity.linvel = Vec3::new(0.0, velocity.linvel.y, 0.0);
-                    velocity.angvel = Vec3::ZERO;
+                    player.state = if stance.crouched { PlayerState::Sneaking } else { PlayerState::Idling };
+                    velocity.set_linvel(Vec3::new(0.0, velocity.linvel().y, 0.0));
+                    velocity.set_angvel(Vec3::ZERO);
                 }
             }
         }
     }
 }
 
-fn ground_check(mut player_query: Query<(&mut Transform, &mut Player, &mut Grounded), With<Player>>,
-                rapier_context: Res<RapierContext>
+fn ground_check(time: Res<Time>,
+                mut player_query: Query<(Entity, &Transform, &mut Player, &mut Grounded, &Stance, BodyVelocityReadOnly), With<Player>>,
+                physics_query: PhysicsQuery
 ) {
-    for (transform, mut player, mut grounded) in player_query.iter_mut() {
-        let ray_start = transform.translation + Vec3::new(0.0, 0.15, 0.0);
-        if let Some((_entity, _toi)) = rapier_context.cast_ray(
-            ray_start, Vec3::NEG_Y, 0.2, true, QueryFilter::only_fixed()
-        ) {
-            grounded.0 = true;
-            if player.state == PlayerState::Jumping {
-                player.state = PlayerState::Idling;
-            }
-        } else {
-            grounded.0 = false;
+    for (entity, transform, mut player, mut grounded, stance, velocity) in player_query.iter_mut() {
+        // The probe has to fit into the box, also while crouching.
+        let radius = GROUND_PROBE_RADIUS.min(stance.half_height - 0.05);
+        let distance = stance.half_height - radius + GROUND_PROBE_TOLERANCE;
+        let hit = physics_query.cast_sphere_down(transform.translation, radius, distance, entity);
+        grounded.update(hit, time.delta_seconds());
+
+        // Only end the jump on the way down, the probe still hits the ground in the jump frame.
+        if grounded.on_ground && player.state == PlayerState::Jumping && velocity.linvel().y <= 0.0 {
+            player.state = PlayerState::Idling;
         }
     }
 }
 
+/// Angle in degrees between the ground normal and the up axis.
+fn slope_angle(normal: Vec3) -> f32 {
+    normal.angle_between(Vec3::Y).to_degrees()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit_with_normal(normal: Vec3) -> Option<ShapeHit> {
+        Some(ShapeHit {
+            entity: Entity::from_raw(1),
+            distance: 0.05,
+            normal,
+        })
+    }
+
+    /// Unit Test for check the slope angle of flat and 45 degree ground.
+    #[test]
+    fn test_slope_angle() {
+        assert!(slope_angle(Vec3::Y).abs() < 0.001);
+        assert!((slope_angle(Vec3::new(1.0, 1.0, 0.0).normalize()) - 45.0).abs() < 0.001);
+    }
+
+    /// Unit Test for check if steep slopes are reported but do not count as ground.
+    #[test]
+    fn test_grounded_steep_slope() {
+        let mut grounded = Grounded::default();
+        grounded.update(hit_with_normal(Vec3::new(1.0, 0.3, 0.0).normalize()), 0.016);
+
+        assert!(!grounded.on_ground);
+        assert!(grounded.slope_angle > MAX_WALKABLE_SLOPE);
+        assert_eq!(grounded.surface, Some(Entity::from_raw(1)));
+    }
+
+    /// Unit Test for check if the coyote time allows a late jump and expires.
+    #[test]
+    fn test_grounded_coyote_time() {
+        let mut grounded = Grounded::default();
+        grounded.update(hit_with_normal(Vec3::Y), 0.016);
+        assert!(grounded.on_ground);
+
+        grounded.update(None, COYOTE_TIME * 0.5);
+        assert!(!grounded.on_ground);
+        assert!(grounded.can_jump());
+
+        grounded.update(None, COYOTE_TIME);
+        assert!(!grounded.can_jump());
+    }
+
+    /// Unit Test for check if a buffered jump expires and is consumed only once.
+    #[test]
+    fn test_grounded_jump_buffer() {
+        let mut grounded = Grounded::default();
+        grounded.buffer_jump();
+        assert!(grounded.jump_buffered());
+
+        grounded.consume_jump();
+        assert!(!grounded.jump_buffered());
+        assert!(!grounded.can_jump());
+
+        grounded.buffer_jump();
+        grounded.update(None, JUMP_BUFFER_TIME + 0.01);
+        assert!(!grounded.jump_buffered());
+    }
+}
@@ -0,0 +1,179 @@
+use bevy::prelude::*;
+use crate::entities::player::{Player, PlayerDamaged, PlayerState};
+use crate::entities::player::player_input::Grounded;
+use crate::manager::PlayerSets;
+use crate::physics::{BodyVelocity, BodyVelocityItem};
+
+/// Falls below this height in meters are soft landings without any effect.
+const SOFT_LANDING_HEIGHT: f32 = 4.0;
+/// Falls from this height on deal damage scaled against the max health.
+const DAMAGE_LANDING_HEIGHT: f32 = 8.0;
+/// Falls from this height on are always lethal.
+const LETHAL_LANDING_HEIGHT: f32 = 20.0;
+/// Time in seconds the player is locked in [`PlayerState::Grounded`] after a hard landing.
+const HARD_LANDING_RECOVERY: f32 = 0.6;
+
+/// Tracks the airborne phase of the player for the landing evaluation.
+#[derive(Component, Reflect, Debug)]
+#[reflect(Component)]
+pub struct FallTracker {
+    pub airborne: bool,
+    pub airborne_time: f32,
+    pub peak_height: f32,
+    pub impact_speed: f32,
+    pub recovery_timer: f32,
+    /// Ignore the next landing, used for the drop after spawning.
+    pub protected: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LandingOutcome {
+    Soft,
+    Hard,
+    Damage(f32),
+    Death,
+}
+
+impl Default for FallTracker {
+    fn default() -> Self {
+        Self {
+            airborne: false,
+            airborne_time: 0.0,
+            peak_height: 0.0,
+            impact_speed: 0.0,
+            recovery_timer: 0.0,
+            protected: true,
+        }
+    }
+}
+
+pub struct PlayerLandingPlugin;
+
+impl Plugin for PlayerLandingPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<FallTracker>();
+
+        app.add_systems(Update, (track_airborne, update_landing_recovery).chain().in_set(PlayerSets));
+    }
+}
+
+fn track_airborne(time: Res<Time>,
+                  mut damaged_event_writer: EventWriter<PlayerDamaged>,
+                  mut player_query: Query<(Entity, &Transform, &Grounded, &mut FallTracker, &mut Player, BodyVelocity)>
+) {
+    for (entity, transform, grounded, mut tracker, mut player, mut velocity) in player_query.iter_mut() {
+        let height = transform.translation.y;
+
+        // Climbing is no fall, the tracking starts again after the dismount or respawn.
+        if player.state == PlayerState::Climbing || player.state == PlayerState::Dead {
+            tracker.airborne = false;
+            continue;
+        }
+
+        if !grounded.on_ground {
+            if !tracker.airborne {
+                tracker.airborne = true;
+                tracker.airborne_time = 0.0;
+                tracker.peak_height = height;
+            } else {
+                tracker.airborne_time += time.delta_seconds();
+                tracker.peak_height = tracker.peak_height.max(height);
+            }
+            tracker.impact_speed = (-velocity.linvel().y).max(0.0);
+            continue;
+        }
+
+        if !tracker.airborne {
+            continue;
+        }
+        tracker.airborne = false;
+
+        if tracker.protected {
+            tracker.protected = false;
+            continue;
+        }
+
+        let fall_height = (tracker.peak_height - height).max(0.0);
+        let outcome = landing_outcome(fall_height, player.base.max_health);
+
+        match outcome {
+            LandingOutcome::Soft => {}
+            LandingOutcome::Hard => {
+                lock_after_landing(&mut player, &mut tracker, &mut velocity);
+            }
+            LandingOutcome::Damage(amount) => {
+                player.base.current_stats.health = (player.base.current_stats.health - amount).max(0.0);
+                damaged_event_writer.send(PlayerDamaged { entity, amount });
+                lock_after_landing(&mut player, &mut tracker, &mut velocity);
+            }
+            LandingOutcome::Death => {
+                let amount = player.base.current_stats.health;
+                player.base.current_stats.health = 0.0;
+                damaged_event_writer.send(PlayerDamaged { entity, amount });
+            }
+        }
+
+        info!("Player landed from {:.2}m after {:.2}s with {:.2}m/s: {:?}", fall_height, tracker.airborne_time, tracker.impact_speed, outcome);
+    }
+}
+
+fn update_landing_recovery(time: Res<Time>,
+                           mut player_query: Query<(&mut FallTracker, &mut Player)>
+) {
+    for (mut tracker, mut player) in player_query.iter_mut() {
+        if tracker.recovery_timer <= 0.0 {
+            continue;
+        }
+
+        tracker.recovery_timer -= time.delta_seconds();
+        if tracker.recovery_timer <= 0.0 && player.state == PlayerState::Grounded {
+            player.state = PlayerState::Idling;
+        }
+    }
+}
+
+fn lock_after_landing(player: &mut Player, tracker: &mut FallTracker, velocity: &mut BodyVelocityItem) {
+    player.state = PlayerState::Grounded;
+    tracker.recovery_timer = HARD_LANDING_RECOVERY;
+    velocity.set_linvel(Vec3::new(0.0, velocity.linvel().y, 0.0));
+    velocity.set_angvel(Vec3::ZERO);
+}
+
+/// Evaluate a landing. The damage grows linear from [`DAMAGE_LANDING_HEIGHT`]
+/// up to the full `max_health` at [`LETHAL_LANDING_HEIGHT`].
+fn landing_outcome(fall_height: f32, max_health: f32) -> LandingOutcome {
+    if fall_height < SOFT_LANDING_HEIGHT {
+        LandingOutcome::Soft
+    } else if fall_height < DAMAGE_LANDING_HEIGHT {
+        LandingOutcome::Hard
+    } else if fall_height < LETHAL_LANDING_HEIGHT {
+        let scale = (fall_height - DAMAGE_LANDING_HEIGHT) / (LETHAL_LANDING_HEIGHT - DAMAGE_LANDING_HEIGHT);
+        LandingOutcome::Damage(max_health * scale)
+    } else {
+        LandingOutcome::Death
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check the landing tiers.
+    #[test]
+    fn test_landing_outcome_tiers() {
+        assert_eq!(landing_outcome(1.0, 450.0), LandingOutcome::Soft);
+        assert_eq!(landing_outcome(5.0, 450.0), LandingOutcome::Hard);
+        assert_eq!(landing_outcome(14.0, 450.0), LandingOutcome::Damage(225.0));
+        assert_eq!(landing_outcome(LETHAL_LANDING_HEIGHT, 450.0), LandingOutcome::Death);
+    }
+
+    /// Unit Test for check if the damage never reaches the max health below the lethal height.
+    #[test]
+    fn test_landing_damage_below_max_health() {
+        if let LandingOutcome::Damage(amount) = landing_outcome(LETHAL_LANDING_HEIGHT - 0.01, 450.0) {
+            assert!(amount < 450.0);
+        } else {
+            panic!("Unexpected landing outcome");
+        }
+    }
+}
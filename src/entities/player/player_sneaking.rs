@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use crate::entities::NoiseEmitter;
+use crate::entities::player::{Player, PlayerState};
+use crate::entities::player::player_input::InputAction;
+use crate::manager::PlayerSets;
+use crate::physics::{player_collider, BodyVelocityReadOnly, PhysicsQuery};
+
+/// Half size of the player box while standing.
+pub const STANDING_HALF_EXTENTS: Vec3 = Vec3::splat(0.5);
+/// Half height of the player box while sneaking.
+const CROUCHED_HALF_HEIGHT: f32 = 0.3;
+/// Half height of the box cast over the player before he stands up. His
+/// footprint is shrunk by it too, so touching walls do not block.
+const STAND_UP_SKIN: f32 = 0.05;
+
+/// Collider height of the player. Toggled by [`InputAction::Sneak`].
+#[derive(Component, Reflect, Debug)]
+#[reflect(Component)]
+pub struct Stance {
+    pub crouched: bool,
+    pub half_height: f32,
+}
+
+impl Default for Stance {
+    fn default() -> Self {
+        Self {
+            crouched: false,
+            half_height: STANDING_HALF_EXTENTS.y,
+        }
+    }
+}
+
+pub struct PlayerSneakingPlugin;
+
+impl Plugin for PlayerSneakingPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Stance>();
+
+        app.add_systems(Update, (update_stance, update_player_noise).in_set(PlayerSets));
+    }
+}
+
+/// Toggle the stance on [`InputAction::Sneak`]. Sprint and jump inputs stand
+/// the player up first, if there is enough room above him.
+fn update_stance(mut commands: Commands,
+                 mut input_event_reader: EventReader<InputAction>,
+                 physics_query: PhysicsQuery,
+                 mut player_query: Query<(Entity, &mut Transform, &mut Stance, &Player)>
+) {
+    for event in input_event_reader.read() {
+        for (entity, mut transform, mut stance, player) in player_query.iter_mut() {
+            if player.state.blocks_input() {
+                continue;
+            }
+
+            match event {
+                InputAction::Sneak if !stance.crouched => {
+                    set_stance(&mut commands, entity, &mut transform, &mut stance, true);
+                }
+                InputAction::Sneak | InputAction::Sprinting(_) | InputAction::Jump if stance.crouched => {
+                    // The box has the footprint of the standing player and starts below his top.
+                    let origin = transform.translation + Vec3::Y * (stance.half_height - STAND_UP_SKIN);
+                    let half_extents = (STANDING_HALF_EXTENTS - Vec3::splat(STAND_UP_SKIN)).with_y(STAND_UP_SKIN);
+                    let clearance = (STANDING_HALF_EXTENTS.y - stance.half_height) * 2.0;
+
+                    if physics_query.cast_box(origin, half_extents, transform.rotation, Vec3::Y, clearance, entity).is_some() {
+                        debug!("Stand up blocked by a ceiling");
+                        continue;
+                    }
+                    set_stance(&mut commands, entity, &mut transform, &mut stance, false);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Resize the collider and keep the bottom of the box at the same height.
+fn set_stance(commands: &mut Commands, entity: Entity, transform: &mut Transform, stance: &mut Stance, crouched: bool) {
+    let half_height = if crouched { CROUCHED_HALF_HEIGHT } else { STANDING_HALF_EXTENTS.y };
+
+    transform.translation.y += half_height - stance.half_height;
+    commands.entity(entity).insert(player_collider(STANDING_HALF_EXTENTS.with_y(half_height)));
+    stance.crouched = crouched;
+    stance.half_height = half_height;
+}
+
+fn update_player_noise(mut player_query: Query<(&Player, &Stance, BodyVelocityReadOnly, &mut NoiseEmitter)>) {
+    for (player, stance, velocity, mut noise) in player_query.iter_mut() {
+        let moving = velocity.linvel().with_y(0.0).length_squared() > 0.01;
+        let radius = noise_radius(player.state, stance.crouched, moving);
+        if noise.radius != radius {
+            noise.radius = radius;
+        }
+    }
+}
+
+/// Hearing radius in meters of the noise the player makes.
+fn noise_radius(state: PlayerState, crouched: bool, moving: bool) -> f32 {
+    match state {
+        PlayerState::Dead => 0.0,
+        PlayerState::Sprinting | PlayerState::Grounded => 16.0,
+        PlayerState::Dodging | PlayerState::Attacking | PlayerState::Blocking => 10.0,
+        PlayerState::Jumping | PlayerState::Climbing => 6.0,
+        _ if crouched && moving => 3.0,
+        _ if crouched => 0.0,
+        _ if moving => 8.0,
+        _ => 1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if sneaking is quieter than walking and sprinting.
+    #[test]
+    fn test_noise_radius_sneaking() {
+        let sneaking = noise_radius(PlayerState::Sneaking, true, true);
+        let walking = noise_radius(PlayerState::Moving, false, true);
+        let sprinting = noise_radius(PlayerState::Sprinting, false, true);
+
+        assert!(sneaking < walking);
+        assert!(walking < sprinting);
+        assert_eq!(noise_radius(PlayerState::Sneaking, true, false), 0.0);
+    }
+
+    /// Unit Test for check if the box bottom keeps his height while the stance changes.
+    #[test]
+    fn test_set_stance_keeps_bottom() {
+        let mut world = World::new();
+        let entity = world.spawn_empty().id();
+        let mut transform = Transform::from_xyz(0.0, 1.5, 0.0);
+        let mut stance = Stance::default();
+
+        let mut queue = bevy::ecs::world::CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        set_stance(&mut commands, entity, &mut transform, &mut stance, true);
+        assert!(stance.crouched);
+        assert!((transform.translation.y - stance.half_height - 1.0).abs() < 0.001);
+
+        set_stance(&mut commands, entity, &mut transform, &mut stance, false);
+        assert!((transform.translation.y - 1.5).abs() < 0.001);
+        queue.apply(&mut world);
+    }
+}
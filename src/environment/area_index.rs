@@ -0,0 +1,118 @@
+use std::path::Path;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+//################################################# Models #################################################
+/// Index of an area which is split into one glb per chunk, like
+/// `maps/forest/forest.area.toml`. The chunk files are loaded on demand
+/// while they are in streaming radius.
+#[derive(Asset, TypePath, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AreaIndex {
+    pub name: String,
+    /// Edge length of every chunk.
+    pub chunk_size: i32,
+    /// Glb with the gameplay nodes of the area, like checkpoints.
+    #[serde(default)]
+    pub nodes: Option<String>,
+    #[serde(default)]
+    pub chunks: Vec<AreaIndexChunk>,
+}
+
+/// One chunk of the [`AreaIndex`]. `x` and `z` are the world position of the
+/// chunk, the file is relative to the index file. Without file the name of
+/// [`AreaIndex::chunk_file_name`] is used.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AreaIndexChunk {
+    pub x: i32,
+    pub z: i32,
+    #[serde(default)]
+    pub file: String,
+}
+
+#[derive(Default)]
+pub struct AreaIndexLoader;
+
+pub struct AreaIndexPlugin;
+
+//################################################# Implementations #################################################
+impl AreaIndex {
+    /// File name of a chunk like `chunk_512_-1024.glb`.
+    pub fn chunk_file_name(x: i32, z: i32) -> String {
+        format!("chunk_{}_{}.glb", x, z)
+    }
+
+    /// Make every file path of the index relative to the assets folder.
+    pub fn resolve_paths(&mut self, directory: &Path) {
+        let resolve = |file: &str| directory.join(file).to_string_lossy().replace('\\', "/");
+
+        self.nodes = self.nodes.as_deref().map(resolve);
+        for chunk in self.chunks.iter_mut() {
+            if chunk.file.is_empty() {
+                chunk.file = Self::chunk_file_name(chunk.x, chunk.z);
+            }
+            chunk.file = resolve(&chunk.file);
+        }
+    }
+}
+
+impl AssetLoader for AreaIndexLoader {
+    type Asset = AreaIndex;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load<'a>(&'a self,
+                      reader: &'a mut Reader<'_>,
+                      _settings: &'a (),
+                      load_context: &'a mut LoadContext<'_>
+    ) -> Result<AreaIndex, Self::Error> {
+        let mut content = String::new();
+        reader.read_to_string(&mut content).await?;
+
+        let mut index: AreaIndex = toml::from_str(&content)?;
+        index.resolve_paths(load_context.path().parent().unwrap_or(Path::new("")));
+        Ok(index)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["area.toml"]
+    }
+}
+
+impl Plugin for AreaIndexPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<AreaIndex>()
+            .init_asset_loader::<AreaIndexLoader>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if the chunk files are resolved relative to the index file.
+    #[test]
+    fn test_area_index_resolve_paths() {
+        let mut index: AreaIndex = toml::from_str(r#"
+            name = "forest"
+            chunk_size = 512
+            nodes = "nodes.glb"
+
+            [[chunks]]
+            x = 0
+            z = -512
+            file = "chunk_0_-512.glb"
+
+            [[chunks]]
+            x = 512
+            z = 0
+        "#).unwrap();
+
+        index.resolve_paths(Path::new("maps/forest"));
+
+        assert_eq!(index.nodes.as_deref(), Some("maps/forest/nodes.glb"));
+        assert_eq!(index.chunks[0].file, "maps/forest/chunk_0_-512.glb");
+        assert_eq!(index.chunks[1].file, "maps/forest/chunk_512_0.glb");
+    }
+}
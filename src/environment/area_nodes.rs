@@ -0,0 +1,53 @@
+use bevy::asset::LoadState;
+use bevy::gltf::GltfNode;
+use bevy::prelude::*;
+use crate::environment::{Checkpoint, Climbable, FloatingOrigin};
+use crate::environment::chunk_handler::SceneHandleResource;
+
+/// Spawn gameplay marker entities from the named nodes of the area glb.
+/// Nodes are matched by name so designers can place them directly in Blender.
+pub struct AreaNodesPlugin;
+
+impl Plugin for AreaNodesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, spawn_area_nodes.run_if(resource_exists::<SceneHandleResource>));
+    }
+}
+
+fn spawn_area_nodes(mut commands: Commands,
+                    mut spawned: Local<bool>,
+                    asset_server: Res<AssetServer>,
+                    origin: Res<FloatingOrigin>,
+                    scene_handle: Res<SceneHandleResource>,
+                    glb_handle: Res<Assets<Gltf>>,
+                    node_handle: Res<Assets<GltfNode>>,
+) {
+    if *spawned || asset_server.get_load_state(&scene_handle.handle) != Some(LoadState::Loaded) {
+        return;
+    }
+
+    let Some(gltf) = glb_handle.get(&scene_handle.handle) else { return };
+    for node in gltf.nodes.iter().filter_map(|handle| node_handle.get(handle)) {
+        let transform = node.transform.with_translation(origin.to_local(node.transform.translation));
+        if node.name.contains("climb") {
+            commands.spawn((
+                Name::new(node.name.clone()),
+                Climbable,
+                TransformBundle::from_transform(transform),
+            ));
+            info!("Spawn climbable {:?}", node.name);
+        } else if node.name.contains("checkpoint") {
+            commands.spawn((
+                Name::new(node.name.clone()),
+                Checkpoint {
+                    id: node.name.clone(),
+                    activated: false,
+                },
+                TransformBundle::from_transform(transform),
+            ));
+            info!("Spawn checkpoint {:?}", node.name);
+        }
+    }
+
+    *spawned = true;
+}
@@ -1,9 +1,20 @@
 use bevy::prelude::*;
+use crate::entities::BossKilled;
+use crate::environment::WorldFlags;
 
 pub struct EnvironmentBase;
 
 impl Plugin for EnvironmentBase {
-    fn build(&self, _app: &mut App) {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, record_killed_bosses);
+    }
+}
 
+/// Remember defeated bosses in the [`WorldFlags`] so they are part of the save game.
+fn record_killed_bosses(mut boss_event_reader: EventReader<BossKilled>,
+                        mut world_flags: ResMut<WorldFlags>
+) {
+    for event in boss_event_reader.read() {
+        world_flags.killed_bosses.insert(event.id.clone());
     }
-}
\ No newline at end of file
+}
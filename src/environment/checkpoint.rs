@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+use crate::entities::ResetEnemies;
+use crate::entities::player::{Player, PlayerState};
+use crate::environment::{Checkpoint, CheckpointRested, FloatingOrigin, LastCheckpoint};
+use crate::manager::PlayerSets;
+
+/// Max distance in meters between player and checkpoint for resting.
+const REST_DISTANCE: f32 = 2.5;
+
+pub struct CheckpointPlugin;
+
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LastCheckpoint>();
+        app.add_event::<CheckpointRested>();
+
+        // Resting comes first, the climb mount on the same key only gets presses which are not used here.
+        app.add_systems(Update, rest_at_checkpoint.before(PlayerSets));
+    }
+}
+
+/// Rest at the nearest checkpoint if the interact key is pressed. Resting
+/// activates the checkpoint, restores the player stats and resets the enemies.
+/// The press is cleared then, so no other interaction uses it.
+fn rest_at_checkpoint(mut keyboard: ResMut<ButtonInput<KeyCode>>,
+                      origin: Res<FloatingOrigin>,
+                      mut last_checkpoint: ResMut<LastCheckpoint>,
+                      mut rested_event_writer: EventWriter<CheckpointRested>,
+                      mut reset_event_writer: EventWriter<ResetEnemies>,
+                      mut checkpoint_query: Query<(&mut Checkpoint, &GlobalTransform)>,
+                      mut player_query: Query<(&Transform, &mut Player)>
+) {
+    let interact_key = KeyCode::KeyE;
+    if !keyboard.just_pressed(interact_key) {
+        return;
+    }
+
+    for (transform, mut player) in player_query.iter_mut() {
+        if player.state.blocks_input() || player.state == PlayerState::Dodging {
+            continue;
+        }
+
+        let nearest = checkpoint_query.iter_mut()
+            .map(|(checkpoint, checkpoint_transform)| {
+                let position = checkpoint_transform.translation();
+                (checkpoint, position, position.distance(transform.translation))
+            })
+            .filter(|(_, _, distance)| *distance <= REST_DISTANCE)
+            .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b));
+
+        if let Some((mut checkpoint, position, _)) = nearest {
+            if !checkpoint.activated {
+                checkpoint.activated = true;
+                info!("Checkpoint activated {:?}", checkpoint.id);
+            }
+
+            last_checkpoint.id = Some(checkpoint.id.clone());
+            last_checkpoint.position = Some(origin.to_world(position));
+            player.base.restore_stats();
+
+            rested_event_writer.send(CheckpointRested { id: checkpoint.id.clone() });
+            reset_event_writer.send(ResetEnemies);
+            keyboard.clear_just_pressed(interact_key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if resting activates the nearest checkpoint in
+    /// reach, stores it as respawn point, restores the player and takes the
+    /// key press. Out of reach the press is left for the climb mount.
+    #[test]
+    fn test_rest_at_nearest_checkpoint() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_event::<CheckpointRested>()
+            .add_event::<ResetEnemies>();
+        app.init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<LastCheckpoint>()
+            .init_resource::<FloatingOrigin>();
+        app.add_systems(Update, rest_at_checkpoint);
+
+        let near = app.world_mut().spawn((
+            Checkpoint { id: "checkpoint_near".to_string(), activated: false },
+            GlobalTransform::from_translation(Vec3::new(11.0, 0.0, 0.0)),
+        )).id();
+        let far = app.world_mut().spawn((
+            Checkpoint { id: "checkpoint_far".to_string(), activated: false },
+            GlobalTransform::from_translation(Vec3::new(12.0, 0.0, 0.0)),
+        )).id();
+        let mut player = Player::default();
+        player.base.current_stats.health = 1.0;
+        let player = app.world_mut().spawn((Transform::from_xyz(10.0, 0.0, 0.0), player)).id();
+
+        app.world_mut().get_mut::<Transform>(player).unwrap().translation.x = 20.0;
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::KeyE);
+        app.update();
+        assert!(app.world().resource::<LastCheckpoint>().id.is_none());
+        assert!(app.world().resource::<ButtonInput<KeyCode>>().just_pressed(KeyCode::KeyE));
+
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().reset_all();
+        app.world_mut().get_mut::<Transform>(player).unwrap().translation.x = 10.0;
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::KeyE);
+        app.update();
+
+        let world = app.world();
+        assert!(world.get::<Checkpoint>(near).unwrap().activated);
+        assert!(!world.get::<Checkpoint>(far).unwrap().activated);
+        let last_checkpoint = world.resource::<LastCheckpoint>();
+        assert_eq!(last_checkpoint.id.as_deref(), Some("checkpoint_near"));
+        assert_eq!(last_checkpoint.position, Some(Vec3::new(11.0, 0.0, 0.0)));
+        let player = world.get::<Player>(player).unwrap();
+        assert_eq!(player.base.current_stats.health, player.base.max_health);
+        assert_eq!(world.resource::<Events<CheckpointRested>>().len(), 1);
+        assert!(!world.resource::<ButtonInput<KeyCode>>().just_pressed(KeyCode::KeyE));
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashSet;
+use bevy::prelude::*;
+use bevy::render::mesh::Indices;
+use crate::entities::player::Player;
+use crate::environment::{Chunk, ChunkEntered, ChunkExited, ChunkLoaded, ChunkManager, ChunkStreamer, ChunkTaskFailed, ChunkUnloaded, FloatingOrigin};
+use crate::environment::chunk_handler::{get_visible_chunks, CHUNK_SIZE, UNLOAD_DISTANCE, VIEW_DISTANCE};
+use crate::environment::collider_cache::ColliderCache;
+use crate::manager::RunMode;
+use crate::physics::TerrainShape;
+
+/// Size of a baked collider vertex, triangle and height in bytes.
+const COLLIDER_VERTEX_SIZE: usize = 12;
+const COLLIDER_TRIANGLE_SIZE: usize = 12;
+const COLLIDER_HEIGHT_SIZE: usize = 4;
+
+//################################################# Models #################################################
+/// Streaming state of a chunk grid cell, used for the overlay colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkState {
+    /// Grid cell in streaming radius without chunk entry.
+    Unknown,
+    /// Chunk entry which was never requested.
+    MetadataOnly,
+    /// Wanted chunk whose glb or meshes are still loading.
+    Loading,
+    Loaded,
+    /// Hidden chunk of the monolithic area glb, his entity is kept.
+    Dormant,
+    /// Chunk glb which was loaded before and freed on eviction.
+    Evicted,
+}
+
+/// Toggle of the chunk streaming overlay, switched with F3.
+#[derive(Resource, Debug, Default)]
+pub struct ChunkDebugOverlay {
+    pub enabled: bool,
+    /// Chunks whose last event was [`ChunkUnloaded`], the chunk entry does
+    /// not keep anything of a freed glb to tell an evicted chunk apart.
+    pub unloaded: HashSet<(i32, i32)>,
+    /// Chunk and area of the player from the last [`ChunkEntered`].
+    pub player_chunk: Option<((i32, i32), String)>,
+    /// Error of the last failed chunk task.
+    pub last_task_error: Option<String>,
+}
+
+/// Text of the chunk streaming overlay.
+#[derive(Component, Debug)]
+pub struct ChunkDebugHud;
+
+//################################################# Implementations #################################################
+impl ChunkState {
+    /// State of `chunk`, `queued` if the streamer wants it and `evicted` if
+    /// it was loaded before.
+    pub fn of(chunk: &Chunk, queued: bool, evicted: bool) -> Self {
+        if chunk.loaded {
+            ChunkState::Loaded
+        } else if queued || chunk.scene.is_some() {
+            ChunkState::Loading
+        } else if chunk.id.is_some() {
+            ChunkState::Dormant
+        } else if evicted {
+            ChunkState::Evicted
+        } else {
+            ChunkState::MetadataOnly
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            ChunkState::Unknown => Color::srgb(0.4, 0.4, 0.4),
+            ChunkState::MetadataOnly => Color::srgb(0.3, 0.5, 1.0),
+            ChunkState::Loading => Color::srgb(1.0, 0.8, 0.1),
+            ChunkState::Loaded => Color::srgb(0.2, 1.0, 0.3),
+            ChunkState::Dormant => Color::srgb(0.7, 0.3, 1.0),
+            ChunkState::Evicted => Color::srgb(1.0, 0.3, 0.2),
+        }
+    }
+}
+
+/// Gizmo overlay of the chunk grid and the streaming radii, with a text of
+/// the chunk counts and memory estimates. Only added to windowed runs.
+pub struct ChunkDebugPlugin;
+
+impl Plugin for ChunkDebugPlugin {
+    fn build(&self, app: &mut App) {
+        if RunMode::of(app) != RunMode::Windowed {
+            return;
+        }
+
+        app.init_resource::<ChunkDebugOverlay>();
+        app.add_systems(Startup, spawn_chunk_debug_hud);
+        app.add_systems(Update, (
+            toggle_chunk_debug_overlay,
+            track_chunk_events,
+            draw_chunk_debug_gizmos.run_if(chunk_debug_overlay_enabled),
+            update_chunk_debug_hud,
+        ).chain());
+    }
+}
+
+fn chunk_debug_overlay_enabled(overlay: Res<ChunkDebugOverlay>) -> bool {
+    overlay.enabled
+}
+
+fn toggle_chunk_debug_overlay(keyboard: Res<ButtonInput<KeyCode>>,
+                              mut overlay: ResMut<ChunkDebugOverlay>
+) {
+    let overlay_key = KeyCode::F3;
+    if keyboard.just_pressed(overlay_key) {
+        overlay.enabled = !overlay.enabled;
+    }
+}
+
+fn track_chunk_events(mut unloaded_event_reader: EventReader<ChunkUnloaded>,
+                      mut loaded_event_reader: EventReader<ChunkLoaded>,
+                      mut exited_event_reader: EventReader<ChunkExited>,
+                      mut entered_event_reader: EventReader<ChunkEntered>,
+                      mut failed_event_reader: EventReader<ChunkTaskFailed>,
+                      mut overlay: ResMut<ChunkDebugOverlay>
+) {
+    for event in unloaded_event_reader.read() {
+        overlay.unloaded.insert(event.chunk);
+    }
+    for event in loaded_event_reader.read() {
+        overlay.unloaded.remove(&event.chunk);
+    }
+    // The exit is always sent before the enter of the next chunk.
+    for event in exited_event_reader.read() {
+        if overlay.player_chunk.as_ref().is_some_and(|(chunk, area)| *chunk == event.chunk && *area == event.area) {
+            overlay.player_chunk = None;
+        }
+    }
+    for event in entered_event_reader.read() {
+        overlay.player_chunk = Some((event.chunk, event.area.clone()));
+    }
+    for event in failed_event_reader.read() {
+        overlay.last_task_error = Some(format!("{:?}: {}", event.id, event.error));
+    }
+}
+
+fn draw_chunk_debug_gizmos(mut gizmos: Gizmos,
+                           player_query: Query<&Transform, With<Player>>,
+                           origin: Res<FloatingOrigin>,
+                           chunk_manager: Res<ChunkManager>,
+                           streamer: Res<ChunkStreamer>,
+                           overlay: Res<ChunkDebugOverlay>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+    let position = player_transform.translation;
+    let world_position = origin.to_world(position);
+    // The grid lies flat at the height of the player.
+    let rotation = Quat::from_rotation_x(std::f32::consts::FRAC_PI_2);
+    let cell_center = |x: i32, z: i32| origin.to_local(Vec3::new(x as f32, position.y, z as f32));
+
+    for (key, chunk) in chunk_manager.chunk_entries.iter() {
+        let state = ChunkState::of(chunk, streamer.queue.contains(key), overlay.unloaded.contains(key));
+        let size = Vec2::splat(chunk.size as f32 - 4.0);
+        gizmos.rect(cell_center(chunk.x, chunk.z), rotation, size, state.color());
+    }
+
+    for (x, z) in get_visible_chunks(world_position, CHUNK_SIZE) {
+        if !chunk_manager.chunk_entries.contains_key(&(x, z)) {
+            let size = Vec2::splat(CHUNK_SIZE as f32 - 4.0);
+            gizmos.rect(cell_center(x, z), rotation, size, ChunkState::Unknown.color());
+        }
+    }
+
+    // Chunks are kept within the unload distance of the whole predicted path.
+    let predicted_position = origin.to_local(streamer.predicted_position).with_y(position.y);
+    gizmos.circle(predicted_position, Dir3::Y, UNLOAD_DISTANCE, Color::srgb(1.0, 0.3, 0.2)).resolution(64);
+    gizmos.circle(position, Dir3::Y, UNLOAD_DISTANCE, Color::srgb(1.0, 0.3, 0.2)).resolution(64);
+    gizmos.circle(position, Dir3::Y, VIEW_DISTANCE, Color::srgb(0.2, 1.0, 0.3)).resolution(64);
+    gizmos.line(position, predicted_position, Color::srgb(1.0, 0.8, 0.1));
+}
+
+fn spawn_chunk_debug_hud(mut commands: Commands) {
+    let mut hud = TextBundle::from_section("", TextStyle {
+        font_size: 16.0,
+        color: Color::WHITE,
+        ..default()
+    }).with_style(Style {
+        position_type: PositionType::Absolute,
+        top: Val::Px(8.0),
+        right: Val::Px(8.0),
+        ..default()
+    }).with_background_color(Color::srgba(0.0, 0.0, 0.0, 0.7));
+    hud.z_index = ZIndex::Global(i32::MAX);
+    hud.visibility = Visibility::Hidden;
+
+    commands.spawn((Name::new("ChunkDebugHud"), ChunkDebugHud, hud));
+}
+
+fn update_chunk_debug_hud(overlay: Res<ChunkDebugOverlay>,
+                          chunk_manager: Res<ChunkManager>,
+                          streamer: Res<ChunkStreamer>,
+                          collider_cache: Res<ColliderCache>,
+                          meshes: Res<Assets<Mesh>>,
+                          mut hud_query: Query<(&mut Text, &mut Visibility), With<ChunkDebugHud>>
+) {
+    for (mut text, mut visibility) in hud_query.iter_mut() {
+        if !overlay.enabled {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        }
+        visibility.set_if_neq(Visibility::Visible);
+
+        let chunks = chunk_manager.chunk_entries.values();
+        let loaded = chunks.clone().filter(|chunk| chunk.loaded).count();
+        let loading_files = chunks.clone().filter(|chunk| !chunk.loaded && chunk.scene.is_some()).count();
+        let colliders = chunks.clone().filter(|chunk| chunk.has_collider).count();
+
+        // The collision mesh is often the render mesh, every mesh is counted once.
+        let mesh_ids = chunks
+            .filter(|chunk| chunk.id.is_some())
+            .flat_map(|chunk| chunk.lods.iter().chain(chunk.collision.iter()))
+            .map(|handle| handle.id())
+            .collect::<HashSet<_>>();
+        let mesh_bytes = mesh_ids.iter()
+            .filter_map(|id| meshes.get(*id))
+            .map(mesh_size)
+            .sum::<usize>();
+        let heightfield_bytes = chunk_manager.chunk_entries.values()
+            .filter(|chunk| chunk.has_collider)
+            .filter_map(|chunk| chunk.heightfield.as_ref())
+            .map(|heightfield| heightfield.heights.len() * COLLIDER_HEIGHT_SIZE)
+            .sum::<usize>();
+        let collider_bytes = collider_cache.colliders.values()
+            .map(|collider| shape_size(collider))
+            .sum::<usize>() + heightfield_bytes;
+
+        text.sections[0].value = format!(
+            "Chunks: {} known, {} loaded, {} queued, {} with collider\n\
+             Tasks: {} running, {} chunk glb\n\
+             Memory: {:.1} MiB meshes, {:.1} MiB colliders",
+            chunk_manager.chunk_entries.len(), loaded, streamer.queue.len(), colliders,
+            chunk_manager.load_tasks.len(), loading_files,
+            mebibytes(mesh_bytes), mebibytes(collider_bytes),
+        );
+        if let Some((chunk, area)) = &overlay.player_chunk {
+            text.sections[0].value += &format!("\nPlayer: chunk {:?} of area {:?}", chunk, area);
+        }
+        if let Some(error) = &overlay.last_task_error {
+            text.sections[0].value += &format!("\nLast task error: {}", error);
+        }
+    }
+}
+
+/// Estimated size of the vertex and index buffer of `mesh` in bytes.
+fn mesh_size(mesh: &Mesh) -> usize {
+    let vertex_bytes = mesh.count_vertices() * mesh.get_vertex_size() as usize;
+    let index_bytes = match mesh.indices() {
+        Some(indices @ Indices::U16(_)) => indices.len() * 2,
+        Some(indices @ Indices::U32(_)) => indices.len() * 4,
+        None => 0,
+    };
+    vertex_bytes + index_bytes
+}
+
+/// Size of the baked `shape` in bytes.
+fn shape_size(shape: &TerrainShape) -> usize {
+    match shape {
+        TerrainShape::Trimesh(collider) => collider.vertices.len() * COLLIDER_VERTEX_SIZE + collider.indices.len() * COLLIDER_TRIANGLE_SIZE,
+        TerrainShape::Heightfield(heightfield) => heightfield.heights.len() * COLLIDER_HEIGHT_SIZE,
+    }
+}
+
+fn mebibytes(bytes: usize) -> f32 {
+    bytes as f32 / (1024.0 * 1024.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if the overlay state follows the chunk fields.
+    #[test]
+    fn test_chunk_state() {
+        let mut chunk = Chunk {
+            file: Some("chunk_0_0.glb".to_string()),
+            ..Chunk::new((0, 0), CHUNK_SIZE, "debug", "chunk_0_0")
+        };
+
+        assert_eq!(ChunkState::of(&chunk, false, false), ChunkState::MetadataOnly);
+        assert_eq!(ChunkState::of(&chunk, false, true), ChunkState::Evicted);
+        assert_eq!(ChunkState::of(&chunk, true, true), ChunkState::Loading);
+
+        chunk.id = Some(Entity::PLACEHOLDER);
+        assert_eq!(ChunkState::of(&chunk, false, true), ChunkState::Dormant);
+
+        chunk.loaded = true;
+        assert_eq!(ChunkState::of(&chunk, false, true), ChunkState::Loaded);
+    }
+
+    /// Unit Test for check if the overlay follows the chunk and player events.
+    #[test]
+    fn test_track_chunk_events() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_event::<ChunkUnloaded>()
+            .add_event::<ChunkLoaded>()
+            .add_event::<ChunkExited>()
+            .add_event::<ChunkEntered>()
+            .add_event::<ChunkTaskFailed>();
+        app.init_resource::<ChunkDebugOverlay>();
+        app.add_systems(Update, track_chunk_events);
+
+        app.world_mut().send_event(ChunkUnloaded { chunk: (512, 0) });
+        app.world_mut().send_event(ChunkEntered { chunk: (0, 0), area: "debug".to_string() });
+        app.update();
+        let overlay = app.world().resource::<ChunkDebugOverlay>();
+        assert!(overlay.unloaded.contains(&(512, 0)));
+        assert_eq!(overlay.player_chunk, Some(((0, 0), "debug".to_string())));
+
+        app.world_mut().send_event(ChunkLoaded { chunk: (512, 0) });
+        app.world_mut().send_event(ChunkExited { chunk: (0, 0), area: "debug".to_string() });
+        app.update();
+        let overlay = app.world().resource::<ChunkDebugOverlay>();
+        assert!(overlay.unloaded.is_empty());
+        assert!(overlay.player_chunk.is_none());
+    }
+
+    /// Unit Test for check if the mesh size counts the vertices and the index width.
+    #[test]
+    fn test_mesh_size() {
+        let positions = vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]];
+        let mesh = Mesh::new(bevy::render::mesh::PrimitiveTopology::TriangleList, Default::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+
+        assert_eq!(mesh_size(&mesh), 3 * 12);
+        assert_eq!(mesh_size(&mesh.clone().with_inserted_indices(Indices::U16(vec![0, 2, 1]))), 3 * 12 + 3 * 2);
+        assert_eq!(mesh_size(&mesh.with_inserted_indices(Indices::U32(vec![0, 2, 1]))), 3 * 12 + 3 * 4);
+    }
+}
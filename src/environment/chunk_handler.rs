@@ -1,18 +1,123 @@
-use std::collections::HashMap;
-use bevy::asset::LoadState;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
 use bevy::gltf::{GltfMesh, GltfNode};
 use bevy::prelude::*;
-use bevy::tasks::{AsyncComputeTaskPool, Task};
-use bevy::tasks::futures_lite::future;
-use bevy_rapier3d::prelude::*;
 use crate::entities::player::Player;
-use crate::environment::{Chunk};
+use crate::environment::{AreaFile, Chunk, ChunkEntered, ChunkExited, ChunkLoaded, ChunkTaskFailed, ChunkUnloaded, FloatingOrigin};
+use crate::environment::area_index::AreaIndex;
+use crate::environment::chunk_source::{ChunkMeshes, ChunkPrimitive, ChunkSource};
+use crate::environment::chunk_tasks::{ChunkTaskError, ChunkTaskId, ChunkTaskOutput, ChunkTasks};
+use crate::environment::procedural_terrain::ProceduralTerrain;
+use crate::environment::collider_cache::ColliderCache;
+use crate::logic::{AssetFailures, LoadingData};
+use crate::physics::{set_collider_enabled, terrain_body, BodyVelocityReadOnly};
 
+/// Edge length of a chunk in the area file.
+pub(crate) const CHUNK_SIZE: i32 = 512;
+/// Chunks closer than this to the player are loaded.
+pub(crate) const VIEW_DISTANCE: f32 = 800.0;
+/// Chunks farther than this from the player and his predicted path are unloaded.
+pub(crate) const UNLOAD_DISTANCE: f32 = VIEW_DISTANCE;
+/// Part of the distance a chunk in move direction gets as bonus, a chunk
+/// behind the player gets the same as penalty.
+const DIRECTION_WEIGHT: f32 = 0.5;
+
+/// Chunks and streaming of the area. All positions are world positions, a
+/// [`Transform`] is turned into one with [`FloatingOrigin::to_world`].
 #[derive(Component, Resource, Debug, Default)]
 pub struct ChunkManager {
     pub chunk_entries: HashMap<(i32, i32), Chunk>,
-    pub load_tasks: Vec<Task<HashMap<(i32, i32), Chunk>>>,
+    /// Tasks which create the chunk entries of an area or generate a chunk.
+    pub load_tasks: ChunkTasks,
     pub need_update: bool,
+    /// Positions which are streamed in addition to the player, like a respawn point.
+    pub preload_positions: Vec<Vec3>,
+    /// Flat ground which replaces the chunks if the area file failed.
+    pub fallback_ground: Option<Entity>,
+    /// Chunk which contains the player, see [`Chunk::player_inbound`].
+    pub inbound_chunk: Option<(i32, i32)>,
+    /// Chunks whose glb failed or has no terrain, they are never loaded.
+    pub failed_chunks: HashSet<(i32, i32)>,
+}
+
+impl ChunkManager {
+    /// Keep the chunks around `position` loaded until [`ChunkManager::release`] is called.
+    pub fn preload(&mut self, position: Vec3) {
+        if !self.preload_positions.contains(&position) {
+            self.preload_positions.push(position);
+        }
+    }
+
+    pub fn release(&mut self, position: Vec3) {
+        self.preload_positions.retain(|preload| *preload != position);
+    }
+
+    /// Area of the chunk which contains `position`, or of the nearest chunk.
+    pub fn current_area(&self, position: Vec3) -> Option<String> {
+        self.chunk_entries.values()
+            .min_by(|a, b| {
+                let distance_a = Vec2::new(a.x as f32, a.z as f32).distance(position.xz());
+                let distance_b = Vec2::new(b.x as f32, b.z as f32).distance(position.xz());
+                distance_a.total_cmp(&distance_b)
+            })
+            .map(|chunk| chunk.area.clone())
+    }
+
+    /// Key of the chunk which contains `position`. On a shared edge the
+    /// lowest key wins, so the result does not depend on the map order.
+    pub fn chunk_at(&self, position: Vec3) -> Option<(i32, i32)> {
+        self.chunk_entries.iter()
+            .filter(|(_, chunk)| {
+                let half_size = chunk.size as f32 / 2.0;
+                (position.x - chunk.x as f32).abs() <= half_size && (position.z - chunk.z as f32).abs() <= half_size
+            })
+            .map(|(key, _)| *key)
+            .min()
+    }
+
+    /// True if all known chunks in view distance of `position` are loaded.
+    /// Failed chunks are skipped, they would never be ready.
+    pub fn is_area_ready(&self, position: Vec3) -> bool {
+        if self.fallback_ground.is_some() {
+            return true;
+        }
+
+        if self.chunk_entries.is_empty() {
+            return false;
+        }
+
+        get_visible_chunks(position, CHUNK_SIZE).iter()
+            .filter(|key| !self.failed_chunks.contains(key))
+            .filter_map(|key| self.chunk_entries.get(key))
+            .all(|chunk| chunk.loaded)
+    }
+}
+
+/// Predictive chunk streaming. The load radius is extended along the velocity
+/// of the player and the wanted chunks are loaded by priority, at most
+/// [`ChunkStreamer::load_budget`] per frame.
+#[derive(Resource, Debug)]
+pub struct ChunkStreamer {
+    /// Wanted chunks which are not loaded yet, the first entry is loaded next.
+    pub queue: Vec<(i32, i32)>,
+    /// Maximum count of chunks which are loaded or whose glb load is issued in one frame.
+    pub load_budget: usize,
+    /// Seconds the player position is predicted ahead.
+    pub lookahead: f32,
+    /// Position the player is expected at after the lookahead.
+    pub predicted_position: Vec3,
+}
+
+impl Default for ChunkStreamer {
+    fn default() -> Self {
+        Self {
+            queue: Vec::new(),
+            load_budget: 2,
+            lookahead: 3.0,
+            predicted_position: Vec3::ZERO,
+        }
+    }
 }
 
 #[derive(Resource)]
@@ -20,6 +125,12 @@ pub struct SceneHandleResource {
     pub handle: Handle<Gltf>,
 }
 
+/// Index of the area if the [`AreaFile`] is split into one glb per chunk.
+#[derive(Resource)]
+pub struct AreaIndexHandle {
+    pub handle: Handle<AreaIndex>,
+}
+
 struct ChildData {
     #[allow(dead_code)] // Only internal usage.
     name: String,
@@ -32,41 +143,76 @@ pub struct ChunkHandlerPlugin;
 impl Plugin for ChunkHandlerPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(ChunkManager::default());
+        app.init_resource::<ChunkStreamer>()
+            .init_resource::<ColliderCache>()
+            .init_resource::<ChunkSource>();
+        app.add_event::<ChunkEntered>()
+            .add_event::<ChunkExited>()
+            .add_event::<ChunkLoaded>()
+            .add_event::<ChunkUnloaded>()
+            .add_event::<ChunkTaskFailed>();
         app.add_systems(Startup,
             load_save_config_area_file);
 
-        app.add_systems(Update, (create_chunk_loading_task, process_chunk_loading_task_data).after(load_save_config_area_file));
+        app.add_systems(Update, (
+            create_chunk_loading_task.run_if(resource_exists::<SceneHandleResource>.and_then(not(resource_exists::<AreaIndexHandle>))),
+            create_indexed_chunk_loading_task.run_if(resource_exists::<AreaIndexHandle>),
+            process_chunk_loading_task_data
+        ).after(load_save_config_area_file).before(load_chunks));
 
-        app.add_systems(Update, (load_chunks, unload_chunks));
+        // Chained so a chunk is never loaded and unloaded in an unknown order.
+        app.add_systems(Update, (mark_failed_chunks, load_chunks, unload_chunks, update_inbound_chunk).chain());
+        app.add_systems(Update, spawn_fallback_ground);
     }
 }
 
 fn load_save_config_area_file(mut commands: Commands,
                               asset_server: Res<AssetServer>,
+                              area_file: Res<AreaFile>,
+                              mut source: ResMut<ChunkSource>,
+                              mut loading_data: ResMut<LoadingData>,
                               mut chunk_manager: ResMut<ChunkManager>,
 ) {
-    let scene_area_handle= asset_server.load("maps/debug.glb");
-    commands.insert_resource(SceneHandleResource{handle: scene_area_handle.clone()});
-
-    info!("Load scene config area from {:?}", scene_area_handle);
     chunk_manager.need_update = true;
+    *source = ChunkSource::from_area_file(&area_file);
+
+    match &*source {
+        ChunkSource::AreaIndex(path) => {
+            let index_handle: Handle<AreaIndex> = asset_server.load(path.clone());
+            loading_data.register(index_handle.clone());
+            commands.insert_resource(AreaIndexHandle { handle: index_handle.clone() });
+
+            info!("Load area index from {:?}", index_handle);
+        }
+        ChunkSource::AreaGlb(path) => {
+            let scene_area_handle= asset_server.load(path.clone());
+            loading_data.register(scene_area_handle.clone());
+            commands.insert_resource(SceneHandleResource{handle: scene_area_handle.clone()});
+
+            info!("Load scene config area from {:?}", scene_area_handle);
+        }
+        // The chunk entries are created by `load_chunks` once they are wanted.
+        ChunkSource::Procedural(terrain) => {
+            info!("Generate procedural terrain with seed {}", terrain.seed);
+        }
+    }
 }
 
+/// Create the chunks of the area glb once it is loaded, the nodes of the glb
+/// are added together with it. The chunks get the name of the area file.
 fn create_chunk_loading_task(
-    asset_server: Res<AssetServer>,
+    area_file: Res<AreaFile>,
     scene_handle: Res<SceneHandleResource>,
     glb_handle: Res<Assets<Gltf>>,
     node_handle: Res<Assets<GltfNode>>,
     mut chunk_manager: ResMut<ChunkManager>,
 ) {
-    let load_state = asset_server.get_load_state(&scene_handle.handle);
-    if load_state != Option::from(LoadState::Loaded) {
+    let Some(gltf) = glb_handle.get(&scene_handle.handle) else {
         return;
-    }
+    };
 
     if chunk_manager.need_update {
-    let node_data: HashMap<String, (Handle<GltfNode>, Vec<ChildData>)> = if let Some(gltf) = glb_handle.get(&scene_handle.handle) {
-        gltf.named_nodes.iter()
+    let node_data: HashMap<String, (Handle<GltfNode>, Vec<ChildData>)> = gltf.named_nodes.iter()
             .filter_map(|(name, handle)| {
                 if let Some(node) = node_handle.get(handle) {
                     let children = node.children.iter()
@@ -83,32 +229,27 @@ fn create_chunk_loading_task(
                     None
                 }
             })
-            .collect()
-    } else {
-        HashMap::new()
-    };
+            .collect();
 
-    let task_pool = AsyncComputeTaskPool::get();
-    let task = task_pool.spawn(async move {
+    let area = area_file.path.clone();
+    let area_name = area_file.name();
+    chunk_manager.load_tasks.spawn(ChunkTaskId::Area(area.clone()), 0.0, async move {
         let mut loaded_chunks = HashMap::new();
 
         for (name, (handle, children)) in node_data.iter() {
             for child in children {
                 let (x, z) = child.translation;
 
+                if child.scale <= 0 {
+                    return Err(ChunkTaskError::InvalidChunkSize { chunk: (x, z), size: child.scale });
+                }
+
                 if !loaded_chunks.contains_key(&(x, z)) {
                     loaded_chunks.insert(
                         (x, z),
                         Chunk {
-                            id: None,
                             node: handle.clone(),
-                            x,
-                            z,
-                            size: child.scale,
-                            loaded: false,
-                            area: "debug".to_string(),
-                            name: name.clone(),
-                            player_inbound: false,
+                            ..Chunk::new((x, z), child.scale, area_name.clone(), name.clone())
                         },
                     );
 
@@ -117,105 +258,458 @@ fn create_chunk_loading_task(
             }
         }
 
-        loaded_chunks
+        if loaded_chunks.is_empty() {
+            return Err(ChunkTaskError::EmptyArea(area));
+        }
+        Ok(ChunkTaskOutput::Chunks(loaded_chunks))
     });
 
-        chunk_manager.load_tasks.push(task);
         chunk_manager.need_update = false;
     }
 }
 
-fn process_chunk_loading_task_data(
-    mut chunk_manager: ResMut<ChunkManager>
+/// Create the chunks of an [`AreaIndex`] and load the glb with the area
+/// nodes. The glb files of the chunks are loaded by [`load_chunks`] once
+/// they are in streaming radius.
+fn create_indexed_chunk_loading_task(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    index_handle: Res<AreaIndexHandle>,
+    area_indices: Res<Assets<AreaIndex>>,
+    mut loading_data: ResMut<LoadingData>,
+    mut chunk_manager: ResMut<ChunkManager>,
 ) {
-    let mut completed_tasks = Vec::new();
+    if !chunk_manager.need_update {
+        return;
+    }
+
+    let Some(index) = area_indices.get(&index_handle.handle) else {
+        return;
+    };
+
+    if index.chunk_size != CHUNK_SIZE {
+        warn!("Area {:?} has chunk size {}, but the streaming uses {}", index.name, index.chunk_size, CHUNK_SIZE);
+    }
 
-    for (i, load_task) in chunk_manager.load_tasks.iter_mut().enumerate() {
-        if let Some(loaded_chunks) = future::block_on(future::poll_once(load_task)) {
-            completed_tasks.push((i, loaded_chunks));
+    if let Some(nodes) = &index.nodes {
+        let scene_area_handle = asset_server.load(nodes.clone());
+        loading_data.register(scene_area_handle.clone());
+        commands.insert_resource(SceneHandleResource { handle: scene_area_handle });
+    }
+
+    info!("Create {} chunks of area {:?}", index.chunks.len(), index.name);
+
+    let index = index.clone();
+    chunk_manager.load_tasks.spawn(ChunkTaskId::Area(index.name.clone()), 0.0, async move {
+        if index.chunks.is_empty() {
+            return Err(ChunkTaskError::EmptyArea(index.name));
         }
+
+        let chunks = index.chunks.iter()
+            .map(|entry| {
+                let name = Path::new(&entry.file).file_stem()
+                    .map_or_else(|| entry.file.clone(), |stem| stem.to_string_lossy().to_string());
+
+                ((entry.x, entry.z), Chunk {
+                    file: Some(entry.file.clone()),
+                    ..Chunk::new((entry.x, entry.z), index.chunk_size, index.name.clone(), name)
+                })
+            })
+            .collect::<HashMap<_, _>>();
+        Ok(ChunkTaskOutput::Chunks(chunks))
+    });
+
+    chunk_manager.need_update = false;
+}
+
+/// Spawn a flat ground if the area file or his chunk task failed, so the
+/// player does not fall forever and the game is still testable. For an
+/// indexed area only the index counts, a failed nodes glb has no terrain.
+fn spawn_fallback_ground(mut commands: Commands,
+                         asset_server: Res<AssetServer>,
+                         asset_failures: Res<AssetFailures>,
+                         mut failed_event_reader: EventReader<ChunkTaskFailed>,
+                         area_file: Res<AreaFile>,
+                         scene_handle: Option<Res<SceneHandleResource>>,
+                         index_handle: Option<Res<AreaIndexHandle>>,
+                         mut meshes: ResMut<Assets<Mesh>>,
+                         mut materials: ResMut<Assets<StandardMaterial>>,
+                         mut chunk_manager: ResMut<ChunkManager>,
+) {
+    let failed = match (index_handle, scene_handle) {
+        (Some(index_handle), _) => asset_failures.is_failed(&asset_server, &index_handle.handle),
+        (None, Some(scene_handle)) => asset_failures.is_failed(&asset_server, &scene_handle.handle),
+        (None, None) => false,
+    };
+    let task_failed = failed_event_reader.read().any(|event| matches!(event.id, ChunkTaskId::Area(_)));
+
+    if chunk_manager.fallback_ground.is_some() || !(failed || task_failed) {
+        return;
     }
 
-    for (i, loaded_chunks) in completed_tasks {
-        let chunk_entries = &mut chunk_manager.chunk_entries;
+    let mesh = Plane3d::default().mesh().size(1024.0, 1024.0).build();
+    let Some(body) = terrain_body(&mesh) else {
+        return;
+    };
+
+    warn!("Area {:?} failed, spawn the fallback ground", area_file.path);
+    let entity = commands.spawn((
+        Name::new("FallbackGround"),
+        PbrBundle {
+            mesh: meshes.add(mesh),
+            material: materials.add(Color::srgb(0.3, 0.3, 0.3)),
+            ..default()
+        },
+        body,
+    )).id();
+
+    chunk_manager.fallback_ground = Some(entity);
+    chunk_manager.need_update = false;
+}
+
+/// Apply the results of all chunk tasks which finished since the last frame.
+fn process_chunk_loading_task_data(mut chunk_manager: ResMut<ChunkManager>,
+                                   mut meshes: ResMut<Assets<Mesh>>,
+                                   mut failed_event_writer: EventWriter<ChunkTaskFailed>,
+) {
+    let chunk_manager = &mut *chunk_manager;
+
+    for (id, result) in chunk_manager.load_tasks.finished() {
+        match (id, result) {
+            (_, Ok(ChunkTaskOutput::Chunks(loaded_chunks))) => {
+                chunk_manager.chunk_entries.extend(loaded_chunks);
+            }
+            (ChunkTaskId::Chunk(key), Ok(ChunkTaskOutput::Terrain(terrain))) => {
+                if let Some(chunk) = chunk_manager.chunk_entries.get_mut(&key) {
+                    chunk.lods = terrain.lods.into_iter().map(|mesh| meshes.add(mesh)).collect();
+                    chunk.heightfield = Some(Arc::new(terrain.heightfield));
+                }
+            }
+            (id, Ok(ChunkTaskOutput::Terrain(_))) => {
+                warn!("Chunk task {:?} generated terrain without chunk", id);
+            }
+            (id, Err(error)) => {
+                warn!("Chunk task {:?} failed: {}", id, error);
+                failed_event_writer.send(ChunkTaskFailed { id, error });
+            }
+        }
+    }
+}
 
-        for (pos, chunk) in loaded_chunks {
-            chunk_entries.insert(pos, chunk);
+/// Mark the chunks whose glb failed or has no terrain mesh as failed, so they
+/// are not requested again and do not block [`ChunkManager::is_area_ready`].
+fn mark_failed_chunks(asset_server: Res<AssetServer>,
+                      asset_failures: Res<AssetFailures>,
+                      glb_handle: Res<Assets<Gltf>>,
+                      node_handle: Res<Assets<GltfNode>>,
+                      mesh_handle: Res<Assets<GltfMesh>>,
+                      mut chunk_manager: ResMut<ChunkManager>,
+                      mut failed_event_writer: EventWriter<ChunkTaskFailed>,
+) {
+    let mut failed = Vec::new();
+    for (key, chunk) in chunk_manager.chunk_entries.iter() {
+        if chunk.loaded || chunk_manager.failed_chunks.contains(key) {
+            continue;
         }
 
-        let _ = chunk_manager.load_tasks.remove(i);
+        let nodes = match (&chunk.file, &chunk.scene) {
+            (Some(file), Some(scene)) if asset_failures.is_failed(&asset_server, scene) => {
+                failed.push((*key, ChunkTaskError::FileFailed(file.clone())));
+                continue;
+            }
+            (Some(_), Some(scene)) => glb_handle.get(scene)
+                .map(|gltf| gltf.nodes.iter().filter_map(|handle| node_handle.get(handle)).collect::<Vec<_>>()),
+            (Some(_), None) => None,
+            (None, _) => node_handle.get(&chunk.node).map(|node| node.children.iter().collect()),
+        };
+
+        // The nodes are `None` while the glb is still loading.
+        if nodes.is_some_and(|nodes| gltf_chunk_meshes(&nodes, &mesh_handle, &Handle::default()).is_none()) {
+            failed.push((*key, ChunkTaskError::MissingTerrain(*key)));
+        }
+    }
+
+    for (key, error) in failed {
+        if let Some(chunk) = chunk_manager.chunk_entries.get_mut(&key) {
+            warn!("Chunk {:?} failed: {}", chunk.name, error);
+            chunk.scene = None;
+        }
+        chunk_manager.failed_chunks.insert(key);
+        failed_event_writer.send(ChunkTaskFailed { id: ChunkTaskId::Chunk(key), error });
     }
 }
 
-fn load_chunks(mut commands: Commands,
-               player_query: Query<&Transform, With<Player>>,
+/// Load the wanted chunks by priority. A chunk with his own glb issues the
+/// load of the file first and is spawned once the glb is loaded, an issued
+/// load uses the budget like a loaded chunk. Procedural chunks are created
+/// once they are wanted and generated on a chunk task.
+pub(crate) fn load_chunks(mut commands: Commands,
+               player_query: Query<(&Transform, Option<BodyVelocityReadOnly>), With<Player>>,
+               asset_server: Res<AssetServer>,
+               origin: Res<FloatingOrigin>,
+               source: Res<ChunkSource>,
+               glb_handle: Res<Assets<Gltf>>,
                node_handle: Res<Assets<GltfNode>>,
                mesh_handle: Res<Assets<GltfMesh>>,
-               meshes: ResMut<Assets<Mesh>>,
+               meshes: Res<Assets<Mesh>>,
+               mut materials: ResMut<Assets<StandardMaterial>>,
+               mut default_material: Local<Option<Handle<StandardMaterial>>>,
                mut chunk_manager: ResMut<ChunkManager>,
-               mut visibility_query: Query<(&mut Visibility, Option<&mut ColliderDisabled>)>,
+               mut streamer: ResMut<ChunkStreamer>,
+               mut loaded_event_writer: EventWriter<ChunkLoaded>,
+               mut visibility_query: Query<&mut Visibility>,
 ) {
-    if let Ok(transform) = player_query.get_single() {
-        let visible_chunks = get_visible_chunks(&transform, 512);
+    let Ok((transform, velocity)) = player_query.get_single() else {
+        return;
+    };
+    let position = origin.to_world(transform.translation);
+    let velocity = velocity.map_or(Vec3::ZERO, |velocity| velocity.linvel());
+    let chunk_manager = &mut *chunk_manager;
+    // Material of the procedural chunks and of glb chunks without one.
+    let default_material = default_material.get_or_insert_with(|| materials.add(ProceduralTerrain::material())).clone();
 
-        for chunk_key in visible_chunks.iter() {
-            let key = (chunk_key.0, chunk_key.1);
-            if let Some(chunk) = chunk_manager.chunk_entries.get_mut(&key) {
-                if chunk.loaded {
-                    continue;
-                }
+    let predicted_position = predict_position(position, velocity, streamer.lookahead);
+    let mut wanted_chunks = get_streamed_chunks(position, predicted_position);
+    for preload in chunk_manager.preload_positions.iter() {
+        wanted_chunks.extend(get_visible_chunks(*preload, CHUNK_SIZE));
+    }
+    wanted_chunks.sort_unstable();
+    wanted_chunks.dedup();
 
-                if let Some(node) = node_handle.get(&chunk.node) {
-                    for child in node.children.iter() {
-                        if child.name.contains("terrain") {
-                            if let Some(mesh_option) = &child.mesh {
-                                if let Some(mesh) = mesh_handle.get(&*mesh_option) {
-                                    load_single_chunk(&mut commands, chunk, &meshes, child, mesh, &mut visibility_query);
-                                }
-                            }
-                        }
-                    }
+    if let ChunkSource::Procedural(terrain) = &*source {
+        for key in wanted_chunks.iter() {
+            chunk_manager.chunk_entries.entry(*key).or_insert_with(|| terrain.chunk(*key, CHUNK_SIZE));
+        }
+    }
+
+    let mut queue = wanted_chunks.into_iter()
+        .filter(|key| !chunk_manager.failed_chunks.contains(key))
+        .filter(|key| chunk_manager.chunk_entries.get(key).map_or(false, |chunk| !chunk.loaded))
+        .collect::<Vec<_>>();
+    queue.sort_by(|a, b| chunk_priority(*a, position, velocity).total_cmp(&chunk_priority(*b, position, velocity)));
+
+    let mut loaded_count = 0;
+    for key in queue.iter() {
+        if loaded_count >= streamer.load_budget {
+            break;
+        }
+
+        let Some(chunk) = chunk_manager.chunk_entries.get_mut(key) else {
+            continue;
+        };
+        let was_loaded = chunk.loaded;
+        let mut issued_load = false;
+
+        if chunk.id.is_some() {
+            show_single_chunk(&mut commands, chunk, &mut visibility_query);
+        } else if let ChunkSource::Procedural(terrain) = &*source {
+            if chunk.lods.is_empty() {
+                let (terrain, x, z, size) = (terrain.clone(), chunk.x, chunk.z, chunk.size);
+                chunk_manager.load_tasks.spawn(ChunkTaskId::Chunk(*key), chunk_priority(*key, position, velocity), async move {
+                    Ok(ChunkTaskOutput::Terrain(terrain.generate(x, z, size)))
+                });
+            } else {
+                let chunk_meshes = ChunkMeshes {
+                    material: default_material.clone(),
+                    lods: chunk.lods.clone(),
+                    primitives: Vec::new(),
+                    collision: None,
+                    heightfield: chunk.heightfield.clone(),
+                };
+                let transform = Transform::from_translation(origin.to_local(Vec3::new(chunk.x as f32, 0.0, chunk.z as f32)));
+                load_single_chunk(&mut commands, chunk, transform, chunk_meshes, &meshes);
+            }
+        } else {
+            let nodes = match chunk.file.clone() {
+                Some(file) => {
+                    let scene = chunk.scene.get_or_insert_with(|| {
+                        issued_load = true;
+                        asset_server.load(file)
+                    }).clone();
+                    glb_handle.get(&scene)
+                        .map(|gltf| gltf.nodes.iter().filter_map(|handle| node_handle.get(handle)).collect::<Vec<_>>())
+                        .unwrap_or_default()
                 }
+                None => node_handle.get(&chunk.node)
+                    .map(|node| node.children.iter().collect::<Vec<_>>())
+                    .unwrap_or_default(),
+            };
+
+            if let Some((child, chunk_meshes)) = gltf_chunk_meshes(&nodes, &mesh_handle, &default_material) {
+                let transform = Transform {
+                    translation: origin.to_local(child.transform.translation),
+                    scale: child.transform.scale,
+                    ..default()
+                };
+                load_single_chunk(&mut commands, chunk, transform, chunk_meshes, &meshes);
             }
         }
+
+        // Chunks with meshes which are still loading do not use the budget.
+        if chunk.loaded || issued_load {
+            loaded_count += 1;
+        }
+        if chunk.loaded && !was_loaded {
+            loaded_event_writer.send(ChunkLoaded { chunk: *key });
+        }
     }
+
+    queue.retain(|key| chunk_manager.chunk_entries.get(key).map_or(false, |chunk| !chunk.loaded));
+    streamer.queue = queue;
+    streamer.predicted_position = predicted_position;
 }
 
 fn unload_chunks(mut commands: Commands,
                  player_query: Query<&Transform, With<Player>>,
+                 origin: Res<FloatingOrigin>,
+                 source: Res<ChunkSource>,
+                 streamer: Res<ChunkStreamer>,
+                 mut collider_cache: ResMut<ColliderCache>,
                  mut chunk_manager: ResMut<ChunkManager>,
-                 mut visibility_query: Query<(&mut Visibility, Option<&mut ColliderDisabled>)>,
+                 mut unloaded_event_writer: EventWriter<ChunkUnloaded>,
+                 mut visibility_query: Query<&mut Visibility>,
 ) {
-    let unload_distance = 800.0;
+    let unload_distance = UNLOAD_DISTANCE;
         if let Ok(transform) = player_query.get_single() {
-            let position = transform.translation;
+            let position = origin.to_world(transform.translation);
+            let chunk_manager = &mut *chunk_manager;
+            let mut unloaded = Vec::new();
 
-            for chunk in chunk_manager.chunk_entries.values_mut() {
+            for (key, chunk) in chunk_manager.chunk_entries.iter_mut() {
                 let chunk_position = Vec3::new(chunk.x as f32, position.y, chunk.z as f32);
-                let distance_to_chunk = position.distance(chunk_position);
+                // Chunks along the predicted path stay, they are needed soon again.
+                let distance_to_chunk = distance_to_path(chunk_position, position, streamer.predicted_position);
+                let preloaded = chunk_manager.preload_positions.iter()
+                    .any(|preload| preload.with_y(0.0).distance(chunk_position.with_y(0.0)) <= unload_distance);
 
-                if distance_to_chunk > unload_distance && chunk.loaded {
+                if distance_to_chunk <= unload_distance || preloaded {
+                    continue;
+                }
+
+                if source.frees_unloaded_chunks() {
+                    // Drop the entity and the mesh handles so the assets of the chunk are freed,
+                    // a chunk which is still generated is not needed anymore.
+                    if chunk_manager.load_tasks.cancel(&ChunkTaskId::Chunk(*key)) {
+                        info!("Cancel the generation of {:?}", chunk.name);
+                    }
+                    if let Some(entity) = chunk.id.take() {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                    chunk.scene = None;
+                    chunk.lods.clear();
+                    chunk.lod = 0;
+                    if let Some(collision) = chunk.collision.take() {
+                        collider_cache.evict(collision.id());
+                    }
+                    chunk.has_collider = false;
+                    chunk.heightfield = None;
+                } else if chunk.loaded {
                     if let Some(entity) = chunk.id {
-                        if let Ok((mut visibility, collider_disable)) = visibility_query.get_mut(entity) {
+                        if let Ok(mut visibility) = visibility_query.get_mut(entity) {
                             *visibility = Visibility::Hidden;
-                            if collider_disable.is_some() {
-                                commands.entity(entity).insert(ColliderDisabled);
-                            }
+                            set_collider_enabled(&mut commands, entity, false);
                         }
                     }
+                }
+
+                if chunk.loaded {
                     chunk.loaded = false;
+                    unloaded.push(*key);
                     info!("Unload {:?}", chunk.name);
                 }
             }
+
+            unloaded.sort_unstable();
+            unloaded_event_writer.send_batch(unloaded.into_iter().map(|chunk| ChunkUnloaded { chunk }));
+        }
+}
+
+/// Set [`Chunk::player_inbound`] of the chunk which contains the player and
+/// send [`ChunkExited`] and [`ChunkEntered`] if he moved into another chunk.
+fn update_inbound_chunk(player_query: Query<&Transform, With<Player>>,
+                        origin: Res<FloatingOrigin>,
+                        mut chunk_manager: ResMut<ChunkManager>,
+                        mut exited_event_writer: EventWriter<ChunkExited>,
+                        mut entered_event_writer: EventWriter<ChunkEntered>,
+) {
+    let Ok(transform) = player_query.get_single() else {
+        return;
+    };
+
+    let inbound_chunk = chunk_manager.chunk_at(origin.to_world(transform.translation));
+    let chunk_manager = &mut *chunk_manager;
+
+    if inbound_chunk != chunk_manager.inbound_chunk {
+        if let Some(key) = chunk_manager.inbound_chunk {
+            if let Some(chunk) = chunk_manager.chunk_entries.get_mut(&key) {
+                chunk.player_inbound = false;
+                exited_event_writer.send(ChunkExited { chunk: key, area: chunk.area.clone() });
+            }
+        }
+
+        if let Some(key) = inbound_chunk {
+            if let Some(chunk) = chunk_manager.chunk_entries.get(&key) {
+                entered_event_writer.send(ChunkEntered { chunk: key, area: chunk.area.clone() });
+            }
         }
+        chunk_manager.inbound_chunk = inbound_chunk;
+    }
+
+    // Chunk entries which are created again start without the flag.
+    if let Some(chunk) = inbound_chunk.and_then(|key| chunk_manager.chunk_entries.get_mut(&key)) {
+        chunk.player_inbound = true;
+    }
+}
+
+/// Position after `lookahead` seconds with the horizontal part of
+/// `velocity`, limited to twice the view distance.
+fn predict_position(position: Vec3, velocity: Vec3, lookahead: f32) -> Vec3 {
+    let travel = (velocity.xz() * lookahead).clamp_length_max(VIEW_DISTANCE * 2.0);
+    position + Vec3::new(travel.x, 0.0, travel.y)
 }
 
-fn get_visible_chunks(player_transform: &Transform, size: i32) -> Vec<(i32, i32)> {
+/// Visible chunks around every chunk step on the way from `position` to
+/// `predicted_position`.
+fn get_streamed_chunks(position: Vec3, predicted_position: Vec3) -> Vec<(i32, i32)> {
+    let mut chunks = get_visible_chunks(position, CHUNK_SIZE);
+
+    let steps = (position.distance(predicted_position) / CHUNK_SIZE as f32).ceil() as i32;
+    for step in 1..=steps {
+        let sample = position.lerp(predicted_position, step as f32 / steps as f32);
+        chunks.extend(get_visible_chunks(sample, CHUNK_SIZE));
+    }
+
+    chunks.sort_unstable();
+    chunks.dedup();
+    chunks
+}
+
+/// Load priority of a chunk, lower is loaded first.
+fn chunk_priority(chunk: (i32, i32), position: Vec3, velocity: Vec3) -> f32 {
+    let offset = Vec2::new(chunk.0 as f32, chunk.1 as f32) - position.xz();
+    let alignment = offset.normalize_or_zero().dot(velocity.xz().normalize_or_zero());
+
+    offset.length() * (1.0 - DIRECTION_WEIGHT * alignment)
+}
+
+/// Horizontal distance of `point` to the line from `start` to `end`.
+fn distance_to_path(point: Vec3, start: Vec3, end: Vec3) -> f32 {
+    let (point, start, end) = (point.xz(), start.xz(), end.xz());
+    let path = end - start;
+    let length_squared = path.length_squared();
+    if length_squared == 0.0 {
+        return point.distance(start);
+    }
+
+    let t = ((point - start).dot(path) / length_squared).clamp(0.0, 1.0);
+    point.distance(start + path * t)
+}
+
+pub(crate) fn get_visible_chunks(player_position: Vec3, size: i32) -> Vec<(i32, i32)> {
     let mut visible_chunks = Vec::new();
 
-    let player_position = player_transform.translation;
     let chunk_size = size;
-    let view_distance = 800.0;
+    let view_distance = VIEW_DISTANCE;
 
     let min_chunk_x = (player_position.x - view_distance).floor() / chunk_size as f32;
     let max_chunk_x = (player_position.x + view_distance).ceil() / chunk_size as f32;
@@ -237,52 +731,371 @@ fn get_visible_chunks(player_transform: &Transform, size: i32) -> Vec<(i32, i32)
     visible_chunks
 }
 
+/// Name without the `_lod<n>` suffix and the level of detail `n`, a name
+/// without suffix is level 0.
+fn split_lod_name(name: &str) -> (&str, usize) {
+    name.rsplit_once("_lod")
+        .and_then(|(base, level)| level.parse().ok().map(|level| (base, level)))
+        .unwrap_or((name, 0))
+}
+
+/// Meshes of the first terrain node in `nodes` whose mesh is loaded. A
+/// `<name>_col` node is the simplified collision mesh of the node `<name>` and
+/// `<name>_lod1`, `<name>_lod2` are his coarser meshes, both are not spawned on their own.
+/// Every primitive is one material, the levels of detail are matched by material.
+/// A terrain without material gets `default_material`.
+fn gltf_chunk_meshes<'a>(nodes: &[&'a GltfNode],
+                         mesh_handle: &Assets<GltfMesh>,
+                         default_material: &Handle<StandardMaterial>
+) -> Option<(&'a GltfNode, ChunkMeshes)> {
+    let node_mesh = |name: &str| nodes.iter()
+        .find(|node| node.name == name)
+        .and_then(|node| node.mesh.as_ref())
+        .and_then(|mesh| mesh_handle.get(mesh));
+
+    nodes.iter().find_map(|child| {
+        let (name, level) = split_lod_name(&child.name);
+        if !name.contains("terrain") || name.ends_with("_col") || level != 0 {
+            return None;
+        }
+
+        let mesh = mesh_handle.get(child.mesh.as_ref()?)?;
+        let levels = (1..).map_while(|level| node_mesh(&format!("{}_lod{}", name, level))).collect::<Vec<_>>();
+        let mut primitives = mesh.primitives.iter().map(|primitive| ChunkPrimitive {
+            material: primitive.material.clone().unwrap_or_else(|| default_material.clone()),
+            lods: std::iter::once(primitive.mesh.clone())
+                .chain(levels.iter().scan(primitive.mesh.clone(), |finer, level| {
+                    if let Some(lod) = level.primitives.iter().find(|lod| lod.material == primitive.material) {
+                        *finer = lod.mesh.clone();
+                    }
+                    Some(finer.clone())
+                }))
+                .collect(),
+        });
+
+        let ChunkPrimitive { material, lods } = primitives.next()?;
+        let collision = node_mesh(&format!("{}_col", name)).map(|collision| collision.primitives[0].mesh.clone());
+
+        Some((*child, ChunkMeshes { material, lods, primitives: primitives.collect(), collision, heightfield: None }))
+    })
+}
+
+/// Show a hidden chunk again.
+fn show_single_chunk(commands: &mut Commands,
+                     chunk: &mut Chunk,
+                     visibility_query: &mut Query<&mut Visibility>
+) {
+    if let Some(entity) = chunk.id {
+        if let Ok(mut visibility) = visibility_query.get_mut(entity) {
+            *visibility = Visibility::Visible;
+            set_collider_enabled(commands, entity, true);
+        }
+    }
+    chunk.loaded = true;
+    info!("Loaded {:?}", chunk.name);
+}
+
+/// Spawn the chunk at `transform` once his finest mesh is loaded. The chunk
+/// is spawned with the finest of the lods and without collider, both are
+/// chosen by the chunk LOD system. The collider is the heightfield if there
+/// is one, otherwise it is baked from the collision mesh. Further materials
+/// are children of the chunk.
 fn load_single_chunk(commands: &mut Commands,
                      chunk: &mut Chunk,
-                     meshes: &ResMut<Assets<Mesh>>,
-                     child: &GltfNode,
-                     mesh: &GltfMesh,
-                     visibility_query: &mut Query<(&mut Visibility, Option<&mut ColliderDisabled>)>
+                     transform: Transform,
+                     chunk_meshes: ChunkMeshes,
+                     meshes: &Assets<Mesh>
 ) {
-    if let Some(material) = &mesh.primitives[0].material {
-        let bevy_mesh = mesh.primitives[0].mesh.clone();
-
-        if let Some(col_mesh) = meshes.get(&bevy_mesh) {
-            if let Some(collider) = Collider::from_bevy_mesh(col_mesh, &ComputedColliderShape::TriMesh) {
-                if chunk.id.is_none() {
-                    let entity_id = commands.spawn((
-                        Name::new(chunk.name.clone()),
-                        PbrBundle {
-                            mesh: bevy_mesh,
-                            transform: Transform {
-                                translation: child.transform.translation,
-                                scale: child.transform.scale,
-                                ..default()
-                            },
-                            visibility: Visibility::Visible,
-                            material: material.clone(),
-                            ..default()
-                        },
-                        RigidBody::Fixed,
-                        collider,
-                    )).id();
+    let bevy_mesh = chunk_meshes.lods[0].clone();
+    if meshes.get(&bevy_mesh).is_none() {
+        return;
+    }
 
-                    chunk.id = Option::from(entity_id);
-                    chunk.loaded = true;
-                    info!("Loaded {:?}", chunk.name);
-                } else {
-                    if let Some(entity) = chunk.id {
-                        if let Ok((mut visibility, collider_disable)) = visibility_query.get_mut(entity) {
-                            *visibility = Visibility::Visible;
-                            if collider_disable.is_some() {
-                                commands.entity(entity).remove::<ColliderDisabled>();
-                            }
-                        }
-                    }
-                    chunk.loaded = true;
-                    info!("Loaded {:?}", chunk.name);
-                }
-            }
+    let entity_id = commands.spawn((
+        Name::new(chunk.name.clone()),
+        PbrBundle {
+            mesh: bevy_mesh.clone(),
+            transform,
+            visibility: Visibility::Visible,
+            material: chunk_meshes.material,
+            ..default()
+        },
+    )).with_children(|parent| {
+        for primitive in chunk_meshes.primitives {
+            parent.spawn(PbrBundle {
+                mesh: primitive.lods[0].clone(),
+                material: primitive.material.clone(),
+                ..default()
+            }).insert(primitive);
+        }
+    }).id();
+
+    chunk.id = Option::from(entity_id);
+    chunk.lods = chunk_meshes.lods;
+    chunk.lod = 0;
+    chunk.collision = Some(chunk_meshes.collision.unwrap_or(bevy_mesh));
+    chunk.heightfield = chunk_meshes.heightfield;
+    chunk.has_collider = false;
+    chunk.loaded = true;
+    info!("Loaded {:?}", chunk.name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::AssetFailure;
+
+    /// Unit Test for check if the streaming reaches ahead of a sprinting player
+    /// and prefers chunks in move direction.
+    #[test]
+    fn test_streaming_ahead_of_player() {
+        let position = Vec3::ZERO;
+        let velocity = Vec3::new(500.0, 0.0, 0.0);
+
+        let predicted_position = predict_position(position, velocity, 3.0);
+        assert_eq!(predicted_position, Vec3::new(1500.0, 0.0, 0.0));
+
+        let chunks = get_streamed_chunks(position, predicted_position);
+        assert!(chunks.contains(&(2048, 0)));
+        assert!(!get_visible_chunks(position, CHUNK_SIZE).contains(&(2048, 0)));
+
+        assert!(chunk_priority((512, 0), position, velocity) < chunk_priority((-512, 0), position, velocity));
+        assert!(distance_to_path(Vec3::new(1024.0, 0.0, 300.0), position, predicted_position) < VIEW_DISTANCE);
+    }
+
+    /// Unit Test for check if the chunk of the player is flagged and the exit
+    /// is sent before the enter if he moves into the next chunk.
+    #[test]
+    fn test_inbound_chunk_events() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_event::<ChunkEntered>()
+            .add_event::<ChunkExited>();
+        app.init_resource::<FloatingOrigin>();
+        app.add_systems(Update, update_inbound_chunk);
+
+        let mut chunk_manager = ChunkManager::default();
+        for (x, area) in [(0, "debug"), (512, "forest")] {
+            chunk_manager.chunk_entries.insert((x, 0), Chunk::new((x, 0), CHUNK_SIZE, area, format!("chunk_{}_0", x)));
         }
+        app.insert_resource(chunk_manager);
+        let player = app.world_mut().spawn((Player::default(), Transform::from_xyz(10.0, 0.0, 0.0))).id();
+
+        app.update();
+        app.world_mut().get_mut::<Transform>(player).unwrap().translation.x = 600.0;
+        app.update();
+
+        let entered = app.world().resource::<Events<ChunkEntered>>();
+        let entered = entered.get_reader().read(entered).map(|event| (event.chunk, event.area.clone())).collect::<Vec<_>>();
+        assert_eq!(entered, vec![((0, 0), "debug".to_string()), ((512, 0), "forest".to_string())]);
+
+        let exited = app.world().resource::<Events<ChunkExited>>();
+        assert_eq!(exited.get_reader().read(exited).map(|event| event.chunk).collect::<Vec<_>>(), vec![(0, 0)]);
+
+        let chunk_manager = app.world().resource::<ChunkManager>();
+        assert!(!chunk_manager.chunk_entries[&(0, 0)].player_inbound);
+        assert!(chunk_manager.chunk_entries[&(512, 0)].player_inbound);
+        assert_eq!(chunk_manager.chunk_at(Vec3::new(256.0, 0.0, 0.0)), Some((0, 0)));
+    }
+
+    /// Unit Test for check if several chunk tasks which finish in the same
+    /// frame are all applied and a failed task is reported.
+    #[test]
+    fn test_process_finished_chunk_tasks() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_event::<ChunkTaskFailed>();
+        app.init_resource::<ChunkManager>()
+            .init_resource::<Assets<Mesh>>();
+        app.add_systems(Update, process_chunk_loading_task_data);
+
+        let terrain = ProceduralTerrain::new(7);
+        let mut chunk_manager = app.world_mut().resource_mut::<ChunkManager>();
+        for (area, x) in [("debug", 0), ("forest", 512)] {
+            let chunk = terrain.chunk((x, 0), CHUNK_SIZE);
+            chunk_manager.load_tasks.spawn(ChunkTaskId::Area(area.to_string()), 0.0, async move {
+                Ok(ChunkTaskOutput::Chunks(HashMap::from([((x, 0), chunk)])))
+            });
+        }
+        chunk_manager.load_tasks.spawn(ChunkTaskId::Chunk((0, 0)), 1.0, async move {
+            Ok(ChunkTaskOutput::Terrain(terrain.generate(0, 0, CHUNK_SIZE)))
+        });
+        chunk_manager.load_tasks.spawn(ChunkTaskId::Area("empty".to_string()), 2.0, async {
+            Err(ChunkTaskError::EmptyArea("empty".to_string()))
+        });
+
+        // Wait until every task is done, so all of them are taken in one frame.
+        while !app.world().resource::<ChunkManager>().load_tasks.all_finished() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        app.update();
+
+        let chunk_manager = app.world().resource::<ChunkManager>();
+        assert_eq!(chunk_manager.load_tasks.len(), 0);
+        assert_eq!(chunk_manager.chunk_entries.len(), 2);
+        assert_eq!(chunk_manager.chunk_entries[&(0, 0)].lods.len(), 3);
+        assert!(chunk_manager.chunk_entries[&(0, 0)].heightfield.is_some());
+        assert!(chunk_manager.chunk_entries[&(512, 0)].lods.is_empty());
+
+        let failed = app.world().resource::<Events<ChunkTaskFailed>>();
+        let failed = failed.get_reader().read(failed).map(|event| event.id.clone()).collect::<Vec<_>>();
+        assert_eq!(failed, vec![ChunkTaskId::Area("empty".to_string())]);
+    }
+
+    /// Unit Test for check if a chunk with a failed glb is marked failed once
+    /// and does not block the area from getting ready.
+    #[test]
+    fn test_failed_chunk_file() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()));
+        app.init_asset::<Gltf>()
+            .init_asset::<GltfNode>()
+            .init_asset::<GltfMesh>();
+        app.add_event::<ChunkTaskFailed>();
+        app.insert_resource(AssetFailures {
+            failures: vec![AssetFailure { path: "maps/forest/missing.glb".to_string(), error: "not found".to_string() }],
+        });
+        app.add_systems(Update, mark_failed_chunks);
+
+        let terrain = ProceduralTerrain::new(7);
+        let mut chunk_manager = ChunkManager::default();
+        for key in get_visible_chunks(Vec3::ZERO, CHUNK_SIZE) {
+            let mut chunk = terrain.chunk(key, CHUNK_SIZE);
+            chunk.loaded = key != (512, 0);
+            chunk_manager.chunk_entries.insert(key, chunk);
+        }
+        let missing = chunk_manager.chunk_entries.get_mut(&(512, 0)).unwrap();
+        missing.file = Some("maps/forest/missing.glb".to_string());
+        missing.scene = Some(app.world().resource::<AssetServer>().load("maps/forest/missing.glb"));
+        assert!(!chunk_manager.is_area_ready(Vec3::ZERO));
+        app.insert_resource(chunk_manager);
+
+        app.update();
+        app.update();
+
+        let chunk_manager = app.world().resource::<ChunkManager>();
+        assert!(chunk_manager.failed_chunks.contains(&(512, 0)));
+        assert!(chunk_manager.chunk_entries[&(512, 0)].scene.is_none());
+        assert!(chunk_manager.is_area_ready(Vec3::ZERO));
+
+        let failed = app.world().resource::<Events<ChunkTaskFailed>>();
+        let failed = failed.get_reader().read(failed).map(|event| event.error.clone()).collect::<Vec<_>>();
+        assert_eq!(failed, vec![ChunkTaskError::FileFailed("maps/forest/missing.glb".to_string())]);
+    }
+
+    /// Unit Test for check if the chunks of an area glb get the name of the area file.
+    #[test]
+    fn test_area_glb_chunks_named_after_file() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()));
+        app.init_asset::<Gltf>()
+            .init_asset::<GltfNode>()
+            .init_resource::<Assets<Mesh>>();
+        app.add_event::<ChunkTaskFailed>();
+        app.insert_resource(AreaFile { path: "maps/forest.glb".to_string() });
+        app.insert_resource(ChunkManager { need_update: true, ..default() });
+        app.add_systems(Update, (create_chunk_loading_task, process_chunk_loading_task_data).chain());
+
+        let node = |name: &str, transform: Transform, children: Vec<GltfNode>| GltfNode {
+            index: 0,
+            name: name.to_string(),
+            asset_label: bevy::gltf::GltfAssetLabel::Node(0),
+            children,
+            mesh: None,
+            transform,
+            extras: None,
+        };
+        let terrain = node("terrain", Transform::from_xyz(512.0, 0.0, 0.0).with_scale(Vec3::splat(256.0)), Vec::new());
+        let chunks = app.world_mut().resource_mut::<Assets<GltfNode>>().add(node("chunk_512_0", Transform::default(), vec![terrain]));
+        let gltf = app.world_mut().resource_mut::<Assets<Gltf>>().add(Gltf {
+            scenes: Vec::new(),
+            named_scenes: default(),
+            meshes: Vec::new(),
+            named_meshes: default(),
+            materials: Vec::new(),
+            named_materials: default(),
+            nodes: vec![chunks.clone()],
+            named_nodes: [("chunk_512_0".into(), chunks)].into_iter().collect(),
+            default_scene: None,
+            animations: Vec::new(),
+            named_animations: default(),
+            source: None,
+        });
+        app.insert_resource(SceneHandleResource { handle: gltf });
+
+        app.update();
+        while !app.world().resource::<ChunkManager>().load_tasks.all_finished() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        app.update();
+
+        let chunk = &app.world().resource::<ChunkManager>().chunk_entries[&(512, 0)];
+        assert_eq!(chunk.area, "forest");
+        assert_eq!(chunk.size, CHUNK_SIZE);
+    }
+
+    /// Unit Test for check if the level of detail is read from the node name.
+    #[test]
+    fn test_split_lod_name() {
+        assert_eq!(split_lod_name("terrain"), ("terrain", 0));
+        assert_eq!(split_lod_name("terrain_lod0"), ("terrain", 0));
+        assert_eq!(split_lod_name("terrain_lod2"), ("terrain", 2));
+        assert_eq!(split_lod_name("terrain_lodge"), ("terrain_lodge", 0));
+    }
+
+    /// Unit Test for check if every material of the terrain is one primitive and
+    /// a level of detail without the material keeps the finer mesh.
+    #[test]
+    fn test_gltf_chunk_meshes_per_material() {
+        use bevy::gltf::{GltfAssetLabel, GltfPrimitive};
+
+        let mut meshes = Assets::<Mesh>::default();
+        let mut materials = Assets::<StandardMaterial>::default();
+        let mut gltf_meshes = Assets::<GltfMesh>::default();
+        let (grass, rock) = (materials.add(StandardMaterial::default()), materials.add(StandardMaterial::default()));
+        let default_material = materials.add(StandardMaterial::default());
+
+        let mut gltf_mesh = |primitives: Vec<(Handle<Mesh>, Option<Handle<StandardMaterial>>)>| gltf_meshes.add(GltfMesh {
+            index: 0,
+            name: String::new(),
+            asset_label: GltfAssetLabel::Mesh(0),
+            primitives: primitives.into_iter()
+                .map(|(mesh, material)| GltfPrimitive {
+                    index: 0,
+                    name: String::new(),
+                    asset_label: GltfAssetLabel::Primitive { mesh: 0, primitive: 0 },
+                    mesh,
+                    material,
+                    extras: None,
+                    material_extras: None,
+                })
+                .collect(),
+            extras: None,
+        });
+        let node = |name: &str, mesh: Handle<GltfMesh>| GltfNode {
+            index: 0,
+            name: name.to_string(),
+            asset_label: GltfAssetLabel::Node(0),
+            children: Vec::new(),
+            mesh: Some(mesh),
+            transform: Transform::default(),
+            extras: None,
+        };
+
+        let fine = [meshes.add(Cuboid::default()), meshes.add(Cuboid::default()), meshes.add(Cuboid::default())];
+        let coarse = meshes.add(Cuboid::default());
+        let nodes = [
+            node("terrain", gltf_mesh(vec![(fine[0].clone(), None), (fine[1].clone(), Some(grass.clone())), (fine[2].clone(), Some(rock.clone()))])),
+            node("terrain_lod1", gltf_mesh(vec![(coarse.clone(), Some(rock.clone()))])),
+        ];
+
+        let (_, chunk_meshes) = gltf_chunk_meshes(&nodes.iter().collect::<Vec<_>>(), &gltf_meshes, &default_material).unwrap();
+
+        assert_eq!(chunk_meshes.material, default_material);
+        assert_eq!(chunk_meshes.lods, vec![fine[0].clone(), fine[0].clone()]);
+        assert_eq!(chunk_meshes.primitives.len(), 2);
+        assert_eq!(chunk_meshes.primitives[0].material, grass);
+        assert_eq!(chunk_meshes.primitives[0].lods, vec![fine[1].clone(), fine[1].clone()]);
+        assert_eq!(chunk_meshes.primitives[1].lods, vec![fine[2].clone(), coarse]);
     }
 }
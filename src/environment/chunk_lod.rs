@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+use crate::entities::player::Player;
+use crate::environment::{Chunk, ChunkManager, FloatingOrigin};
+use crate::environment::chunk_handler::load_chunks;
+use crate::environment::chunk_source::ChunkPrimitive;
+use crate::environment::collider_cache::ColliderCache;
+use crate::physics::{baked_terrain_body, heightfield_body, remove_terrain_body, TerrainShape};
+
+/// Distance from the camera to the chunk edge at which the next coarser
+/// level of detail is shown.
+const LOD_DISTANCES: [f32; 2] = [256.0, 512.0];
+/// Chunks closer than this to the player or a preload position get a collider.
+const COLLISION_DISTANCE: f32 = 128.0;
+/// Colliders of chunks closer than this are read or baked ahead on a task.
+const BAKE_DISTANCE: f32 = COLLISION_DISTANCE * 2.0;
+/// A level or the collider only changes back after the distance moved this
+/// far behind the threshold, so a chunk at the threshold does not flicker.
+const LOD_HYSTERESIS: f32 = 32.0;
+
+/// Select the level of detail of the loaded chunks by camera distance and
+/// give only the chunks near the player a collider.
+pub struct ChunkLodPlugin;
+
+impl Plugin for ChunkLodPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_chunk_lods.after(load_chunks));
+    }
+}
+
+/// Entities spawned by [`load_chunks`] in the same frame are changed over
+/// [`Commands`]. The collider of a chunk is baked from [`BAKE_DISTANCE`] on,
+/// so it is mostly ready once the chunk gets in collision distance.
+fn update_chunk_lods(mut commands: Commands,
+                     camera_query: Query<&GlobalTransform, With<Camera3d>>,
+                     player_query: Query<&Transform, With<Player>>,
+                     children_query: Query<&Children>,
+                     primitive_query: Query<&ChunkPrimitive>,
+                     origin: Res<FloatingOrigin>,
+                     meshes: Res<Assets<Mesh>>,
+                     mut collider_cache: ResMut<ColliderCache>,
+                     mut chunk_manager: ResMut<ChunkManager>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+    let player_position = origin.to_world(player_transform.translation);
+    // Headless runs have no camera, the player is used instead.
+    let camera_position = camera_query.iter().next()
+        .map_or(player_position, |transform| origin.to_world(transform.translation()));
+
+    let chunk_manager = &mut *chunk_manager;
+    let collision_positions = std::iter::once(player_position)
+        .chain(chunk_manager.preload_positions.iter().copied())
+        .collect::<Vec<_>>();
+
+    for chunk in chunk_manager.chunk_entries.values_mut().filter(|chunk| chunk.loaded) {
+        let Some(entity) = chunk.id else {
+            continue;
+        };
+
+        let lod = select_lod(chunk.lod, chunk_distance(chunk, camera_position), chunk.lods.len());
+        if lod != chunk.lod {
+            commands.entity(entity).insert(chunk.lods[lod].clone());
+            for child in children_query.get(entity).into_iter().flatten() {
+                if let Some(mesh) = primitive_query.get(*child).ok().and_then(|primitive| primitive.lods.get(lod)) {
+                    commands.entity(*child).insert(mesh.clone());
+                }
+            }
+            chunk.lod = lod;
+        }
+
+        let collision_distance = collision_positions.iter()
+            .map(|position| chunk_distance(chunk, *position))
+            .fold(f32::MAX, f32::min);
+
+        let baked = if chunk.heightfield.is_none() && !chunk.has_collider && collision_distance <= BAKE_DISTANCE {
+            chunk.collision.as_ref()
+                .and_then(|collision| meshes.get(collision).and_then(|mesh| collider_cache.get_or_bake(collision.id(), mesh)))
+        } else {
+            None
+        };
+
+        let needs_collider = needs_collider(chunk.has_collider, collision_distance);
+        if needs_collider && !chunk.has_collider {
+            if let Some(heightfield) = &chunk.heightfield {
+                commands.entity(entity).insert(heightfield_body(heightfield));
+                chunk.has_collider = true;
+                continue;
+            }
+
+            if let Some(collider) = baked {
+                match collider.as_ref() {
+                    TerrainShape::Trimesh(trimesh) => commands.entity(entity).insert(baked_terrain_body(trimesh)),
+                    TerrainShape::Heightfield(heightfield) => commands.entity(entity).insert(heightfield_body(heightfield)),
+                };
+                chunk.has_collider = true;
+            }
+        } else if !needs_collider && chunk.has_collider {
+            remove_terrain_body(&mut commands, entity);
+            chunk.has_collider = false;
+        }
+    }
+}
+
+/// Horizontal distance from `position` to the edge of the chunk, zero inside of the chunk.
+fn chunk_distance(chunk: &Chunk, position: Vec3) -> f32 {
+    let offset = (position.xz() - Vec2::new(chunk.x as f32, chunk.z as f32)).abs() - Vec2::splat(chunk.size as f32 / 2.0);
+    offset.max(Vec2::ZERO).length()
+}
+
+/// Level of detail for `distance`, starting at the `current` level so the
+/// [`LOD_HYSTERESIS`] applies. `count` is the number of meshes of the chunk.
+fn select_lod(current: usize, distance: f32, count: usize) -> usize {
+    let mut lod = current.min(count.saturating_sub(1));
+
+    while lod + 1 < count && LOD_DISTANCES.get(lod).map_or(false, |threshold| distance > threshold + LOD_HYSTERESIS) {
+        lod += 1;
+    }
+    while lod > 0 && distance < LOD_DISTANCES[lod - 1] - LOD_HYSTERESIS {
+        lod -= 1;
+    }
+
+    lod
+}
+
+fn needs_collider(has_collider: bool, distance: f32) -> bool {
+    if has_collider {
+        distance <= COLLISION_DISTANCE + LOD_HYSTERESIS
+    } else {
+        distance <= COLLISION_DISTANCE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if the level of detail and the collider only change
+    /// after the distance passed the threshold with the hysteresis.
+    #[test]
+    fn test_select_lod_with_hysteresis() {
+        assert_eq!(select_lod(0, 0.0, 3), 0);
+        assert_eq!(select_lod(0, 270.0, 3), 0);
+        assert_eq!(select_lod(0, 300.0, 3), 1);
+        assert_eq!(select_lod(1, 240.0, 3), 1);
+        assert_eq!(select_lod(1, 200.0, 3), 0);
+        assert_eq!(select_lod(0, 2000.0, 3), 2);
+        assert_eq!(select_lod(0, 2000.0, 1), 0);
+
+        assert!(!needs_collider(false, 140.0));
+        assert!(needs_collider(true, 140.0));
+        assert!(!needs_collider(true, 170.0));
+    }
+}
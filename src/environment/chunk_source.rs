@@ -0,0 +1,100 @@
+use std::sync::Arc;
+use bevy::prelude::*;
+use crate::environment::AreaFile;
+use crate::environment::procedural_terrain::ProceduralTerrain;
+use crate::physics::TerrainHeightfield;
+
+//################################################# Models #################################################
+/// Where the chunks of the area come from. Every source creates the same
+/// [`Chunk`](crate::environment::Chunk) entries and [`ChunkMeshes`], so the
+/// streaming does not depend on it.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub enum ChunkSource {
+    /// Chunks are nodes of one area glb.
+    AreaGlb(String),
+    /// Every chunk has his own glb, listed in an [`AreaIndex`](crate::environment::area_index::AreaIndex).
+    AreaIndex(String),
+    /// Chunks are generated around the player, see [`ProceduralTerrain`].
+    Procedural(ProceduralTerrain),
+}
+
+/// Meshes a chunk is spawned with.
+#[derive(Debug, Clone)]
+pub struct ChunkMeshes {
+    pub material: Handle<StandardMaterial>,
+    /// Render meshes from the finest to the coarsest level of detail.
+    pub lods: Vec<Handle<Mesh>>,
+    /// Further materials of the terrain, spawned as children of the chunk.
+    pub primitives: Vec<ChunkPrimitive>,
+    /// Mesh the trimesh collider is baked from, the finest render mesh if `None`.
+    pub collision: Option<Handle<Mesh>>,
+    /// Collider which is used instead of a baked trimesh.
+    pub heightfield: Option<Arc<TerrainHeightfield>>,
+}
+
+/// Terrain part with his own material. The chunk LOD system shows the same
+/// level of detail as on the chunk entity.
+#[derive(Component, Debug, Clone)]
+pub struct ChunkPrimitive {
+    pub material: Handle<StandardMaterial>,
+    /// Render meshes like [`ChunkMeshes::lods`], a level without this
+    /// material keeps the finer mesh.
+    pub lods: Vec<Handle<Mesh>>,
+}
+
+//################################################# Implementations #################################################
+impl ChunkSource {
+    /// Source of `area_file`. A path like `procedural:42` generates the
+    /// chunks with the seed 42. The `--area` argument rejects an invalid
+    /// seed, an invalid seed set otherwise falls back to the default area.
+    pub fn from_area_file(area_file: &AreaFile) -> Self {
+        match area_file.procedural_seed() {
+            Some(Ok(seed)) => return ChunkSource::Procedural(ProceduralTerrain::new(seed)),
+            Some(Err(error)) => {
+                error!("Invalid procedural area {:?}: {}, use the default area", area_file.path, error);
+                return ChunkSource::default();
+            }
+            None => {}
+        }
+
+        if area_file.is_indexed() {
+            ChunkSource::AreaIndex(area_file.path.clone())
+        } else {
+            ChunkSource::AreaGlb(area_file.path.clone())
+        }
+    }
+
+    /// True if unloaded chunks are despawned and their meshes freed. The
+    /// chunks of an area glb are only hidden, the glb stays loaded anyway.
+    pub fn frees_unloaded_chunks(&self) -> bool {
+        !matches!(self, ChunkSource::AreaGlb(_))
+    }
+}
+
+impl Default for ChunkSource {
+    fn default() -> Self {
+        ChunkSource::from_area_file(&AreaFile::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if the chunk source is read from the area file path.
+    #[test]
+    fn test_chunk_source_from_area_file() {
+        let source = |path: &str| ChunkSource::from_area_file(&AreaFile { path: path.to_string() });
+
+        assert_eq!(source("maps/debug.glb"), ChunkSource::AreaGlb("maps/debug.glb".to_string()));
+        assert_eq!(source("maps/forest/forest.area.toml"), ChunkSource::AreaIndex("maps/forest/forest.area.toml".to_string()));
+        assert_eq!(source("procedural:42"), ChunkSource::Procedural(ProceduralTerrain::new(42)));
+        assert_eq!(source("maps/procedural_hills.glb"), ChunkSource::AreaGlb("maps/procedural_hills.glb".to_string()));
+        assert_eq!(source("procedural_hills.glb"), ChunkSource::AreaGlb("procedural_hills.glb".to_string()));
+        assert_eq!(source("procedural:hills"), ChunkSource::default());
+        assert_eq!(source("procedural:-1"), ChunkSource::default());
+
+        assert!(!source("maps/debug.glb").frees_unloaded_chunks());
+        assert!(source("procedural:42").frees_unloaded_chunks());
+    }
+}
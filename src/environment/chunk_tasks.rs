@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use bevy::tasks::futures_lite::future;
+use crate::environment::Chunk;
+use crate::environment::procedural_terrain::GeneratedTerrain;
+
+//################################################# Models #################################################
+/// Identifier of a chunk task, at most one task runs per id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ChunkTaskId {
+    /// Creates the chunk entries of the area with this name or file.
+    Area(String),
+    /// Generates the meshes of the chunk with this key.
+    Chunk((i32, i32)),
+}
+
+#[derive(Debug)]
+pub enum ChunkTaskOutput {
+    Chunks(HashMap<(i32, i32), Chunk>),
+    Terrain(GeneratedTerrain),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChunkTaskError {
+    /// The area has no terrain chunk.
+    EmptyArea(String),
+    /// A chunk would have no area, like a terrain node with a scale below one.
+    InvalidChunkSize { chunk: (i32, i32), size: i32 },
+    /// The glb of a chunk is missing or broken.
+    FileFailed(String),
+    /// The glb or the area node of a chunk has no terrain mesh.
+    MissingTerrain((i32, i32)),
+}
+
+#[derive(Debug)]
+struct ChunkTask {
+    id: ChunkTaskId,
+    priority: f32,
+    task: Task<Result<ChunkTaskOutput, ChunkTaskError>>,
+}
+
+/// Running chunk tasks on the [`AsyncComputeTaskPool`]. Finished tasks are
+/// taken all at once and in priority order, lower is first like the chunk
+/// load priority.
+#[derive(Debug, Default)]
+pub struct ChunkTasks {
+    tasks: Vec<ChunkTask>,
+}
+
+//################################################# Implementations #################################################
+impl Display for ChunkTaskError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChunkTaskError::EmptyArea(area) => write!(f, "area {:?} has no terrain chunk", area),
+            ChunkTaskError::InvalidChunkSize { chunk, size } => write!(f, "chunk {:?} has the invalid size {}", chunk, size),
+            ChunkTaskError::FileFailed(file) => write!(f, "chunk file {:?} failed to load", file),
+            ChunkTaskError::MissingTerrain(chunk) => write!(f, "chunk {:?} has no terrain mesh", chunk),
+        }
+    }
+}
+
+impl std::error::Error for ChunkTaskError {}
+
+impl ChunkTasks {
+    /// Run `future` as task `id`. If the task is running already only his
+    /// priority is updated.
+    pub fn spawn(&mut self,
+                 id: ChunkTaskId,
+                 priority: f32,
+                 future: impl Future<Output = Result<ChunkTaskOutput, ChunkTaskError>> + Send + 'static
+    ) {
+        if let Some(running) = self.tasks.iter_mut().find(|running| running.id == id) {
+            running.priority = priority;
+            return;
+        }
+
+        let task = AsyncComputeTaskPool::get().spawn(future);
+        self.tasks.push(ChunkTask { id, priority, task });
+    }
+
+    /// Cancel the task `id`, a dropped task stops at his next await point.
+    /// Returns false if there is no such task.
+    pub fn cancel(&mut self, id: &ChunkTaskId) -> bool {
+        let count = self.tasks.len();
+        self.tasks.retain(|running| running.id != *id);
+        self.tasks.len() != count
+    }
+
+    /// Take the results of all finished tasks in priority order.
+    pub fn finished(&mut self) -> Vec<(ChunkTaskId, Result<ChunkTaskOutput, ChunkTaskError>)> {
+        let mut finished = Vec::new();
+        self.tasks.retain_mut(|running| match future::block_on(future::poll_once(&mut running.task)) {
+            Some(result) => {
+                finished.push((running.priority, running.id.clone(), result));
+                false
+            }
+            None => true,
+        });
+
+        finished.sort_by(|a, b| a.0.total_cmp(&b.0));
+        finished.into_iter().map(|(_, id, result)| (id, result)).collect()
+    }
+
+    /// True if every task is done, their results are not taken yet. Tests
+    /// wait for it, so several tasks are taken in the same frame.
+    #[cfg(test)]
+    pub fn all_finished(&self) -> bool {
+        self.tasks.iter().all(|running| running.task.is_finished())
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::tasks::TaskPool;
+
+    /// Unit Test for check if a task runs once per id, is cancelled on request
+    /// and the finished tasks are taken by priority.
+    #[test]
+    fn test_chunk_tasks_cancel_and_priority() {
+        AsyncComputeTaskPool::get_or_init(TaskPool::default);
+        let mut tasks = ChunkTasks::default();
+
+        tasks.spawn(ChunkTaskId::Chunk((0, 0)), 2.0, std::future::pending());
+        tasks.spawn(ChunkTaskId::Chunk((0, 0)), 1.0, async { Ok(ChunkTaskOutput::Chunks(HashMap::new())) });
+        tasks.spawn(ChunkTaskId::Chunk((512, 0)), 0.0, async { Err(ChunkTaskError::EmptyArea("debug".to_string())) });
+        assert_eq!(tasks.len(), 2);
+
+        assert!(tasks.cancel(&ChunkTaskId::Chunk((0, 0))));
+        assert!(!tasks.cancel(&ChunkTaskId::Chunk((0, 0))));
+        assert_eq!(tasks.len(), 1);
+
+        tasks.spawn(ChunkTaskId::Area("forest".to_string()), 5.0, async { Ok(ChunkTaskOutput::Chunks(HashMap::new())) });
+        while !tasks.all_finished() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        let finished = tasks.finished();
+        assert_eq!(tasks.len(), 0);
+        assert_eq!(finished.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>(),
+                   vec![ChunkTaskId::Chunk((512, 0)), ChunkTaskId::Area("forest".to_string())]);
+        assert!(matches!(&finished[0].1, Err(ChunkTaskError::EmptyArea(_))));
+    }
+}
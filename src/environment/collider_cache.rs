@@ -0,0 +1,268 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use bevy::tasks::futures_lite::future;
+use crate::physics::{TerrainCollider, TerrainHeightfield, TerrainShape};
+
+/// Version of the cache file layout. Increase it on every change of the
+/// layout or of the baking, old files are not found anymore and baked again.
+const CACHE_VERSION: u32 = 2;
+const CACHE_MAGIC: &[u8; 4] = b"MCOL";
+const KIND_TRIMESH: u32 = 0;
+const KIND_HEIGHTFIELD: u32 = 1;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+//################################################# Models #################################################
+/// Baked terrain shapes of the chunk meshes. A shape is baked once and
+/// stored as `<hash>.col` of his mesh inside of the `directory`, later starts
+/// read the file instead of the mesh. Reading and baking run on the
+/// [`AsyncComputeTaskPool`]. Grid meshes are baked into a heightfield, see
+/// [`TerrainShape::from_mesh`].
+#[derive(Resource, Debug)]
+pub struct ColliderCache {
+    /// Directory of the cache files, [`None`] keeps the cache in memory only.
+    pub directory: Option<PathBuf>,
+    /// Shapes of the meshes in use, removed with [`ColliderCache::evict`].
+    pub colliders: HashMap<AssetId<Mesh>, Arc<TerrainShape>>,
+    tasks: HashMap<AssetId<Mesh>, Task<Option<TerrainShape>>>,
+    /// Meshes without usable geometry, they are not baked again.
+    unusable: HashSet<AssetId<Mesh>>,
+}
+
+//################################################# Implementations #################################################
+impl Default for ColliderCache {
+    fn default() -> Self {
+        let directory = dirs::cache_dir()
+            .map(|directory| directory.join("mira-game").join("colliders"));
+
+        Self::new(directory)
+    }
+}
+
+impl ColliderCache {
+    pub fn new(directory: Option<PathBuf>) -> Self {
+        Self {
+            directory,
+            colliders: HashMap::new(),
+            tasks: HashMap::new(),
+            unusable: HashSet::new(),
+        }
+    }
+
+    /// Shape of the mesh `id`. The first call starts reading the cache file
+    /// or baking `mesh` on a task, [`None`] is returned until it is done.
+    pub fn get_or_bake(&mut self, id: AssetId<Mesh>, mesh: &Mesh) -> Option<Arc<TerrainShape>> {
+        if let Some(collider) = self.colliders.get(&id) {
+            return Some(collider.clone());
+        }
+        if self.unusable.contains(&id) {
+            return None;
+        }
+
+        let Some(task) = self.tasks.get_mut(&id) else {
+            let (directory, mesh) = (self.directory.clone(), mesh.clone());
+            let task = AsyncComputeTaskPool::get().spawn(async move { read_or_bake(directory, &mesh) });
+            self.tasks.insert(id, task);
+            return None;
+        };
+
+        let collider = future::block_on(future::poll_once(task))?;
+        self.tasks.remove(&id);
+        let Some(collider) = collider else {
+            warn!("Mesh {:?} has no usable geometry for a collider", id);
+            self.unusable.insert(id);
+            return None;
+        };
+
+        let collider = Arc::new(collider);
+        self.colliders.insert(id, collider.clone());
+        Some(collider)
+    }
+
+    /// Drop the shape of the mesh `id` and cancel his running bake, used when
+    /// the chunk of the mesh is freed. The cache file stays.
+    pub fn evict(&mut self, id: AssetId<Mesh>) {
+        self.colliders.remove(&id);
+        self.tasks.remove(&id);
+        self.unusable.remove(&id);
+    }
+}
+
+/// Shape of `mesh` from the cache file or baked, a baked shape is written to
+/// the cache file.
+fn read_or_bake(directory: Option<PathBuf>, mesh: &Mesh) -> Option<TerrainShape> {
+    let hash = mesh_hash(mesh)?;
+    let path = directory.map(|directory| directory.join(format!("{:016x}.col", hash)));
+    let cached = path.as_ref()
+        .and_then(|path| fs::read(path).ok())
+        .and_then(|bytes| decode_collider(&bytes));
+    if cached.is_some() {
+        return cached;
+    }
+
+    let collider = TerrainShape::from_mesh(mesh)?;
+    if let Some(path) = path {
+        if let Err(error) = write_cache_file(&path, &encode_collider(&collider)) {
+            warn!("Failed to write collider cache {:?}: {}", path, error);
+        }
+    }
+    Some(collider)
+}
+
+/// FNV-1a hash of the positions and indices of `mesh`. It is stable between
+/// builds, unlike the std hasher.
+fn mesh_hash(mesh: &Mesh) -> Option<u64> {
+    let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION)?.as_float3()?;
+
+    let mut hash = FNV_OFFSET;
+    let mut write = |value: u32| {
+        for byte in value.to_le_bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    };
+
+    write(CACHE_VERSION);
+    positions.iter().flatten().for_each(|value| write(value.to_bits()));
+    if let Some(indices) = mesh.indices() {
+        indices.iter().for_each(|index| write(index as u32));
+    }
+
+    Some(hash)
+}
+
+/// Layout: magic, version and kind, then for a trimesh the vertex count,
+/// triangle count, vertices and triangles, for a heightfield the row count,
+/// column count, size and heights. All little endian.
+fn encode_collider(shape: &TerrainShape) -> Vec<u8> {
+    let mut bytes = CACHE_MAGIC.to_vec();
+    let words = match shape {
+        TerrainShape::Trimesh(collider) => {
+            [CACHE_VERSION, KIND_TRIMESH, collider.vertices.len() as u32, collider.indices.len() as u32].into_iter()
+                .chain(collider.vertices.iter().flat_map(|vertex| vertex.to_array()).map(f32::to_bits))
+                .chain(collider.indices.iter().flatten().copied())
+                .collect::<Vec<_>>()
+        }
+        TerrainShape::Heightfield(heightfield) => {
+            [CACHE_VERSION, KIND_HEIGHTFIELD, heightfield.rows as u32, heightfield.columns as u32].into_iter()
+                .chain(heightfield.size.to_array().map(f32::to_bits))
+                .chain(heightfield.heights.iter().map(|height| height.to_bits()))
+                .collect::<Vec<_>>()
+        }
+    };
+    bytes.extend(words.iter().flat_map(|word| word.to_le_bytes()));
+    bytes
+}
+
+/// Read a cache file, [`None`] if the file is broken or of another version.
+fn decode_collider(bytes: &[u8]) -> Option<TerrainShape> {
+    let body = bytes.strip_prefix(CACHE_MAGIC)?;
+    let words = body.chunks_exact(4)
+        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+        .collect::<Vec<_>>();
+
+    let [version, kind, first_count, second_count, data @ ..] = words.as_slice() else {
+        return None;
+    };
+    let (first_count, second_count) = (*first_count as usize, *second_count as usize);
+    if *version != CACHE_VERSION || body.len() % 4 != 0 {
+        return None;
+    }
+
+    match *kind {
+        KIND_TRIMESH => decode_trimesh(first_count, second_count, data).map(TerrainShape::Trimesh),
+        KIND_HEIGHTFIELD => decode_heightfield(first_count, second_count, data).map(TerrainShape::Heightfield),
+        _ => None,
+    }
+}
+
+fn decode_trimesh(vertex_count: usize, triangle_count: usize, data: &[u32]) -> Option<TerrainCollider> {
+    if data.len() != (vertex_count + triangle_count) * 3 {
+        return None;
+    }
+
+    let (vertices, indices) = data.split_at(vertex_count * 3);
+    let collider = TerrainCollider {
+        vertices: vertices.chunks_exact(3)
+            .map(|vertex| Vec3::new(f32::from_bits(vertex[0]), f32::from_bits(vertex[1]), f32::from_bits(vertex[2])))
+            .collect(),
+        indices: indices.chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect(),
+    };
+
+    let valid = collider.indices.iter().flatten().all(|index| (*index as usize) < vertex_count);
+    valid.then_some(collider)
+}
+
+fn decode_heightfield(rows: usize, columns: usize, data: &[u32]) -> Option<TerrainHeightfield> {
+    let [size_x, size_z, heights @ ..] = data else {
+        return None;
+    };
+    if rows < 2 || columns < 2 || heights.len() != rows * columns {
+        return None;
+    }
+
+    Some(TerrainHeightfield {
+        heights: heights.iter().map(|height| f32::from_bits(*height)).collect(),
+        rows,
+        columns,
+        size: Vec2::new(f32::from_bits(*size_x), f32::from_bits(*size_z)),
+    })
+}
+
+/// Write into a temporary file first, so a crash never leaves a half file.
+fn write_cache_file(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+
+    let temporary = path.with_extension("col.tmp");
+    fs::write(&temporary, bytes)?;
+    fs::rename(temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::tasks::TaskPool;
+
+    /// Unit Test for check if a trimesh and a heightfield survive the cache
+    /// file round trip, broken files are rejected and a freed mesh is evicted.
+    #[test]
+    fn test_collider_cache_roundtrip() {
+        AsyncComputeTaskPool::get_or_init(TaskPool::default);
+        let mesh = Plane3d::default().mesh().size(4.0, 4.0).build();
+        let id = Handle::<Mesh>::default().id();
+        let mut cache = ColliderCache::new(None);
+
+        let collider = loop {
+            if let Some(collider) = cache.get_or_bake(id, &mesh) {
+                break collider;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        };
+        assert!(matches!(collider.as_ref(), TerrainShape::Heightfield(_)));
+        assert!(Arc::ptr_eq(&collider, &cache.get_or_bake(id, &mesh).unwrap()));
+        assert_eq!(mesh_hash(&mesh), mesh_hash(&mesh.clone()));
+
+        cache.evict(id);
+        assert!(cache.colliders.is_empty());
+        assert!(cache.get_or_bake(id, &mesh).is_none());
+        cache.evict(id);
+        assert!(cache.tasks.is_empty());
+
+        let trimesh = TerrainShape::Trimesh(TerrainCollider::from_mesh(&mesh).unwrap());
+        for shape in [collider.as_ref(), &trimesh] {
+            let bytes = encode_collider(shape);
+            assert_eq!(decode_collider(&bytes).as_ref(), Some(shape));
+            assert_eq!(decode_collider(&bytes[..bytes.len() - 4]), None);
+        }
+        assert_eq!(decode_collider(b"MCOL"), None);
+    }
+}
@@ -0,0 +1,151 @@
+use bevy::math::I64Vec2;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::entities::player::Player;
+use crate::environment::chunk_handler::CHUNK_SIZE;
+
+/// The world is recentered once the player is this many cells away from
+/// the origin cell, so the render and physics values stay small.
+const RECENTER_CELLS: i64 = 2;
+
+//################################################# Models #################################################
+/// High precision position in the world. `cell` counts chunk sizes like the
+/// chunk keys and `offset` is the position relative to the cell center, so
+/// the f32 part stays small at any distance.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct GlobalPosition {
+    pub cell: I64Vec2,
+    pub offset: Vec3,
+}
+
+/// Cell the render and physics space is centered on. Every [`Transform`] is
+/// relative to this cell, while the chunk keys, the streaming positions,
+/// the checkpoints and the saves use world positions.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq)]
+pub struct FloatingOrigin {
+    pub cell: I64Vec2,
+}
+
+//################################################# Implementations #################################################
+impl FloatingOrigin {
+    /// World position of the origin cell center.
+    pub fn offset(&self) -> Vec3 {
+        cell_translation(self.cell)
+    }
+
+    /// World position of the [`Transform`] `translation`.
+    pub fn to_world(self, translation: Vec3) -> Vec3 {
+        translation + self.offset()
+    }
+
+    /// [`Transform`] translation of the world position `world`.
+    pub fn to_local(self, world: Vec3) -> Vec3 {
+        world - self.offset()
+    }
+
+    pub fn global_position(&self, translation: Vec3) -> GlobalPosition {
+        let step = cell_step(translation);
+        GlobalPosition {
+            cell: self.cell + step,
+            offset: translation - cell_translation(step),
+        }
+    }
+
+    /// [`Transform`] translation of `position`, the cell difference is
+    /// calculated in i64 so only the result is rounded.
+    pub fn local_position(&self, position: &GlobalPosition) -> Vec3 {
+        cell_translation(position.cell - self.cell) + position.offset
+    }
+}
+
+/// Recenter the world around the player. Every root entity is shifted, that
+/// covers the chunks, the area nodes, the camera and the player. The physics
+/// backends move their bodies to the changed [`Transform`].
+pub struct FloatingOriginPlugin;
+
+impl Plugin for FloatingOriginPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FloatingOrigin>();
+
+        // PreUpdate, so all commands of the last frame are applied and every
+        // system of this frame sees the same origin.
+        app.add_systems(PreUpdate, recenter_floating_origin);
+    }
+}
+
+fn recenter_floating_origin(mut origin: ResMut<FloatingOrigin>,
+                            mut root_query: Query<(&mut Transform, Has<Player>), (Without<Parent>, Without<Node>)>
+) {
+    let Some(translation) = root_query.iter()
+        .find(|(_, is_player)| *is_player)
+        .map(|(transform, _)| transform.translation) else {
+        return;
+    };
+
+    let step = recenter_step(translation);
+    if step == I64Vec2::ZERO {
+        return;
+    }
+
+    let shift = cell_translation(step);
+    for (mut transform, _) in root_query.iter_mut() {
+        transform.translation -= shift;
+    }
+
+    origin.cell += step;
+    info!("Recenter the floating origin to cell {:?}", origin.cell);
+}
+
+/// Cells the origin has to move for the player at `translation`, zero while
+/// he is closer than [`RECENTER_CELLS`].
+fn recenter_step(translation: Vec3) -> I64Vec2 {
+    let step = cell_step(translation);
+    if step.abs().max_element() < RECENTER_CELLS {
+        return I64Vec2::ZERO;
+    }
+
+    step
+}
+
+/// Cell of `translation` relative to the cell it is measured from.
+fn cell_step(translation: Vec3) -> I64Vec2 {
+    (translation.xz() / CHUNK_SIZE as f32).round().as_i64vec2()
+}
+
+fn cell_translation(cell: I64Vec2) -> Vec3 {
+    let translation = cell * CHUNK_SIZE as i64;
+    Vec3::new(translation.x as f32, 0.0, translation.y as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if the world is shifted by whole cells once the
+    /// player is far from the origin and the global position is kept.
+    #[test]
+    fn test_recenter_floating_origin() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(FloatingOriginPlugin);
+
+        let player = app.world_mut().spawn((Player::default(), Transform::from_xyz(1300.0, 5.0, -20.0))).id();
+        let chunk = app.world_mut().spawn(Transform::from_xyz(1024.0, 0.0, 0.0)).id();
+
+        let global_position = FloatingOrigin::default().global_position(Vec3::new(1300.0, 5.0, -20.0));
+        assert_eq!(global_position.cell, I64Vec2::new(3, 0));
+        assert_eq!(global_position.offset, Vec3::new(-236.0, 5.0, -20.0));
+
+        app.update();
+
+        let origin = *app.world().resource::<FloatingOrigin>();
+        assert_eq!(origin.cell, I64Vec2::new(3, 0));
+        assert_eq!(app.world().get::<Transform>(player).unwrap().translation, Vec3::new(-236.0, 5.0, -20.0));
+        assert_eq!(app.world().get::<Transform>(chunk).unwrap().translation, Vec3::new(-512.0, 0.0, 0.0));
+        assert_eq!(origin.global_position(Vec3::new(-236.0, 5.0, -20.0)), global_position);
+        assert_eq!(origin.local_position(&global_position), Vec3::new(-236.0, 5.0, -20.0));
+        assert_eq!(origin.to_world(Vec3::new(-512.0, 0.0, 0.0)), Vec3::new(1024.0, 0.0, 0.0));
+
+        assert_eq!(recenter_step(Vec3::new(700.0, 0.0, 0.0)), I64Vec2::ZERO);
+    }
+}
@@ -1,10 +1,34 @@
+mod area_index;
+mod area_nodes;
 mod base;
+mod checkpoint;
+mod chunk_debug;
 mod chunk_handler;
+mod chunk_lod;
+mod chunk_source;
+mod chunk_tasks;
+mod collider_cache;
+mod floating_origin;
+mod procedural_terrain;
 
 use bevy::gltf::GltfNode;
+use std::collections::BTreeSet;
+use std::sync::Arc;
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::environment::area_index::AreaIndexPlugin;
+use crate::environment::area_nodes::AreaNodesPlugin;
 use crate::environment::base::EnvironmentBase;
+use crate::environment::checkpoint::CheckpointPlugin;
+use crate::environment::chunk_debug::ChunkDebugPlugin;
 use crate::environment::chunk_handler::ChunkHandlerPlugin;
+use crate::environment::chunk_lod::ChunkLodPlugin;
+use crate::environment::chunk_tasks::{ChunkTaskError, ChunkTaskId};
+use crate::environment::floating_origin::FloatingOriginPlugin;
+use crate::physics::TerrainHeightfield;
+
+pub use crate::environment::chunk_handler::{ChunkManager, ChunkStreamer};
+pub use crate::environment::floating_origin::{FloatingOrigin, GlobalPosition};
 
 #[derive(Component, Resource, Reflect, Debug, Clone)]
 #[reflect(Component)]
@@ -17,13 +41,220 @@ pub struct Chunk {
     pub loaded: bool,
     pub area: String,
     pub name: String,
-    pub player_inbound: bool
+    pub player_inbound: bool,
+    /// Own glb of the chunk if the area has an [`AreaIndex`], `None` if the
+    /// chunk is a node of the monolithic area glb.
+    pub file: Option<String>,
+    /// Handle of [`Chunk::file`] while the chunk is in streaming radius. It
+    /// is dropped on eviction so the glb is freed.
+    pub scene: Option<Handle<Gltf>>,
+    /// Render meshes from the finest to the coarsest level of detail.
+    pub lods: Vec<Handle<Mesh>>,
+    /// Index of the shown mesh in [`Chunk::lods`].
+    pub lod: usize,
+    /// Mesh the collider is baked from. The chunk has no collider while it
+    /// is out of the collision distance.
+    pub collision: Option<Handle<Mesh>>,
+    pub has_collider: bool,
+    /// Collider of a generated chunk, used instead of [`Chunk::collision`].
+    #[reflect(ignore)]
+    pub heightfield: Option<Arc<TerrainHeightfield>>,
+}
+
+impl Chunk {
+    /// Unloaded chunk entry at the grid cell `key`, without node or file.
+    pub fn new(key: (i32, i32), size: i32, area: impl Into<String>, name: impl Into<String>) -> Self {
+        let (x, z) = key;
+        Self {
+            id: None,
+            node: Handle::default(),
+            x,
+            z,
+            size,
+            loaded: false,
+            area: area.into(),
+            name: name.into(),
+            player_inbound: false,
+            file: None,
+            scene: None,
+            lods: Vec::new(),
+            lod: 0,
+            collision: None,
+            has_collider: false,
+            heightfield: None,
+        }
+    }
+}
+
+/// Volume the player can climb, like a ladder or a ledge wall. The volume is
+/// the unit cube of the entity [`Transform`], so the scale is the half size.
+/// Authored as node with `climb` in his name at the area glb.
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+pub struct Climbable;
+
+impl Climbable {
+    /// True if the world `point` is inside of the climbable volume.
+    pub fn contains(transform: &GlobalTransform, point: Vec3) -> bool {
+        let local = transform.affine().inverse().transform_point3(point);
+        local.abs().cmple(Vec3::ONE).all()
+    }
+
+    /// World position of the top and bottom center of the volume.
+    pub fn top_and_bottom(transform: &GlobalTransform) -> (Vec3, Vec3) {
+        (transform.transform_point(Vec3::Y), transform.transform_point(Vec3::NEG_Y))
+    }
+}
+
+/// Bonfire like rest point. Authored as node with `checkpoint` in his name
+/// at the area glb, the node name is used as id.
+#[derive(Component, Reflect, Debug, Default)]
+#[reflect(Component)]
+pub struct Checkpoint {
+    pub id: String,
+    pub activated: bool,
+}
+
+/// Prefix of an [`AreaFile`] path which generates the chunks, followed by the seed.
+const PROCEDURAL_PREFIX: &str = "procedural:";
+
+/// Area glb inside of the assets folder the chunks are streamed from. Can be
+/// changed with the `--area` argument. A path to an [`AreaIndex`] like
+/// `maps/forest/forest.area.toml` loads every chunk from his own glb and
+/// `procedural:<seed>` generates the terrain from the seed.
+#[derive(Resource, Debug, Clone)]
+pub struct AreaFile {
+    pub path: String,
+}
+
+impl AreaFile {
+    /// True if the area is split into one glb per chunk.
+    pub fn is_indexed(&self) -> bool {
+        self.path.ends_with(".area.toml")
+    }
+
+    /// Seed of a procedural area path like `procedural:42`. `None` if the
+    /// path is no procedural area, an error if the seed is no `u64`.
+    pub fn procedural_seed(&self) -> Option<Result<u64, String>> {
+        let seed = self.path.strip_prefix(PROCEDURAL_PREFIX)?;
+        Some(seed.parse().map_err(|_| format!("`{}` is no seed, expected `{}<u64>`", seed, PROCEDURAL_PREFIX)))
+    }
+
+    /// Name of the area, the file name without extensions like `debug` for `maps/debug.glb`.
+    pub fn name(&self) -> String {
+        let file_name = self.path.rsplit(['/', '\\']).next().unwrap_or_default();
+        file_name.split('.').next().unwrap_or_default().to_string()
+    }
+}
+
+impl Default for AreaFile {
+    fn default() -> Self {
+        Self { path: "maps/debug.glb".to_string() }
+    }
+}
+
+/// The checkpoint the player rested at last. The player respawns here.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct LastCheckpoint {
+    pub id: Option<String>,
+    /// World position, see [`FloatingOrigin::to_local`].
+    pub position: Option<Vec3>,
+}
+
+/// Persistent state of the world which is stored in the save game.
+#[derive(Resource, Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldFlags {
+    pub opened_doors: BTreeSet<String>,
+    pub killed_bosses: BTreeSet<String>,
+}
+
+/// Send if the player rests at a [`Checkpoint`].
+#[derive(Event, Debug, Clone)]
+pub struct CheckpointRested {
+    pub id: String,
+}
+
+/// Send if the player enters a chunk, always after the [`ChunkExited`] of
+/// the chunk he left.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ChunkEntered {
+    pub chunk: (i32, i32),
+    pub area: String,
+}
+
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ChunkExited {
+    pub chunk: (i32, i32),
+    pub area: String,
+}
+
+/// Send once a chunk is spawned or shown again. The chunks of one frame are
+/// sent in load priority order.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct ChunkLoaded {
+    pub chunk: (i32, i32),
+}
+
+/// Send once a chunk is hidden or freed. The chunks of one frame are sent
+/// sorted by their key, so headless runs always send the same order.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct ChunkUnloaded {
+    pub chunk: (i32, i32),
+}
+
+/// Send if a chunk task failed or a chunk can not be spawned. A failed area
+/// task spawns the fallback ground, a failed chunk is skipped by the streaming.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ChunkTaskFailed {
+    pub id: ChunkTaskId,
+    pub error: ChunkTaskError,
 }
 
 pub struct EnvironmentPlugin;
 
 impl Plugin for EnvironmentPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((EnvironmentBase, ChunkHandlerPlugin));
+        app.init_resource::<WorldFlags>()
+            .init_resource::<AreaFile>();
+        app.register_type::<Climbable>()
+            .register_type::<Checkpoint>();
+        app.add_plugins((EnvironmentBase, FloatingOriginPlugin, AreaIndexPlugin, ChunkHandlerPlugin, ChunkLodPlugin, ChunkDebugPlugin, AreaNodesPlugin, CheckpointPlugin));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if the area name is the file name without extensions.
+    #[test]
+    fn test_area_file_name() {
+        assert_eq!(AreaFile { path: "maps/debug.glb".to_string() }.name(), "debug");
+        assert_eq!(AreaFile { path: "maps/forest/forest.area.toml".to_string() }.name(), "forest");
     }
-}
\ No newline at end of file
+
+    /// Unit Test for check the climbable volume of a scaled and rotated node.
+    #[test]
+    fn test_climbable_contains() {
+        let transform = GlobalTransform::from(Transform {
+            translation: Vec3::new(10.0, 4.0, 0.0),
+            rotation: Quat::from_rotation_y(std::f32::consts::FRAC_PI_2),
+            scale: Vec3::new(0.5, 4.0, 2.0),
+        });
+
+        assert!(Climbable::contains(&transform, Vec3::new(11.5, 7.5, 0.0)));
+        assert!(!Climbable::contains(&transform, Vec3::new(10.0, 8.5, 0.0)));
+        assert!(!Climbable::contains(&transform, Vec3::new(10.0, 4.0, 1.0)));
+
+        let (top, bottom) = Climbable::top_and_bottom(&transform);
+        assert!((top - Vec3::new(10.0, 8.0, 0.0)).length() < 0.001);
+        assert!((bottom - Vec3::new(10.0, 0.0, 0.0)).length() < 0.001);
+    }
+
+    /// Unit Test for check if only area index files load the chunks from their own glb.
+    #[test]
+    fn test_area_file_is_indexed() {
+        assert!(AreaFile { path: "maps/forest/forest.area.toml".to_string() }.is_indexed());
+        assert!(!AreaFile::default().is_indexed());
+    }
+}
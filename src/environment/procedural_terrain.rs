@@ -0,0 +1,237 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use crate::environment::Chunk;
+use crate::physics::TerrainHeightfield;
+
+/// Grid cells per chunk edge of every level of detail, from the finest to the
+/// coarsest. The heightfield collider uses the finest grid.
+const LOD_RESOLUTIONS: [usize; 3] = [64, 32, 16];
+/// Height difference between the lowest and the highest possible point.
+const TERRAIN_HEIGHT: f32 = 120.0;
+/// Size of the largest hills in world units.
+const FEATURE_SIZE: f32 = 600.0;
+/// Noise layers, every layer has half the size and height of the one before.
+const OCTAVES: usize = 5;
+
+const GRADIENTS: [Vec2; 8] = [
+    Vec2::X, Vec2::NEG_X, Vec2::Y, Vec2::NEG_Y,
+    Vec2::new(std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2),
+    Vec2::new(-std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2),
+    Vec2::new(std::f32::consts::FRAC_1_SQRT_2, -std::f32::consts::FRAC_1_SQRT_2),
+    Vec2::new(-std::f32::consts::FRAC_1_SQRT_2, -std::f32::consts::FRAC_1_SQRT_2),
+];
+
+//################################################# Models #################################################
+/// Endless heightmap terrain from layered perlin noise. The same seed always
+/// gives the same terrain and the heights only depend on the world position,
+/// so the edges of neighbour chunks match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProceduralTerrain {
+    pub seed: u64,
+    /// Shuffled numbers 0 to 255 the noise gradients are picked with.
+    permutation: Vec<u8>,
+}
+
+/// Meshes and collider of a chunk, generated on a chunk task.
+#[derive(Debug)]
+pub struct GeneratedTerrain {
+    /// Render meshes from the finest to the coarsest level of detail.
+    pub lods: Vec<Mesh>,
+    pub heightfield: TerrainHeightfield,
+}
+
+/// Heights of a chunk grid with one extra sample around the edge, so the
+/// normals at the edge are the same as the ones of the neighbour chunk.
+struct HeightGrid {
+    resolution: usize,
+    size: f32,
+    heights: Vec<f32>,
+}
+
+//################################################# Implementations #################################################
+impl ProceduralTerrain {
+    pub fn new(seed: u64) -> Self {
+        let mut permutation = (0..=u8::MAX).collect::<Vec<_>>();
+        permutation.shuffle(&mut StdRng::seed_from_u64(seed));
+
+        Self { seed, permutation }
+    }
+
+    /// Terrain height at the world position `position`. Perlin noise is zero
+    /// at his lattice points, so the world origin is always at height zero.
+    pub fn height(&self, position: Vec2) -> f32 {
+        let mut height = 0.0;
+        let mut amplitude = 0.5;
+        let mut point = position / FEATURE_SIZE;
+
+        for _ in 0..OCTAVES {
+            height += self.perlin(point) * amplitude;
+            amplitude *= 0.5;
+            point *= 2.0;
+        }
+
+        height * TERRAIN_HEIGHT
+    }
+
+    /// Chunk entry of the grid cell `key`, his meshes are generated on a chunk task once it is wanted.
+    pub fn chunk(&self, key: (i32, i32), size: i32) -> Chunk {
+        Chunk::new(key, size, "procedural", format!("terrain_{}_{}", key.0, key.1))
+    }
+
+    /// Generate the render meshes and the heightfield of the chunk at `x`, `z`.
+    pub fn generate(&self, x: i32, z: i32, size: i32) -> GeneratedTerrain {
+        let grids = LOD_RESOLUTIONS.iter()
+            .map(|resolution| self.grid(x, z, size, *resolution))
+            .collect::<Vec<_>>();
+
+        GeneratedTerrain {
+            lods: grids.iter().map(HeightGrid::mesh).collect(),
+            heightfield: grids[0].heightfield(),
+        }
+    }
+
+    pub fn material() -> StandardMaterial {
+        StandardMaterial {
+            base_color: Color::srgb(0.33, 0.42, 0.24),
+            perceptual_roughness: 0.9,
+            ..default()
+        }
+    }
+
+    fn grid(&self, x: i32, z: i32, size: i32, resolution: usize) -> HeightGrid {
+        let step = size as f32 / resolution as f32;
+        let start = Vec2::new(x as f32, z as f32) - Vec2::splat(size as f32 / 2.0 + step);
+        let samples = resolution + 3;
+
+        let heights = (0..samples)
+            .flat_map(|row| (0..samples).map(move |column| Vec2::new(column as f32, row as f32)))
+            .map(|sample| self.height(start + sample * step))
+            .collect();
+
+        HeightGrid { resolution, size: size as f32, heights }
+    }
+
+    fn perlin(&self, point: Vec2) -> f32 {
+        let cell = point.floor();
+        let local = point - cell;
+        let (x, z) = (cell.x as i64, cell.y as i64);
+
+        let corner = |dx: i64, dz: i64| self.gradient(x + dx, z + dz).dot(local - Vec2::new(dx as f32, dz as f32));
+        let fade = local * local * local * (local * (local * 6.0 - 15.0) + 10.0);
+
+        let bottom = lerp(corner(0, 0), corner(1, 0), fade.x);
+        let top = lerp(corner(0, 1), corner(1, 1), fade.x);
+        lerp(bottom, top, fade.y)
+    }
+
+    fn gradient(&self, x: i64, z: i64) -> Vec2 {
+        let x = self.permutation[x.rem_euclid(256) as usize] as i64;
+        let hash = self.permutation[(x + z).rem_euclid(256) as usize];
+        GRADIENTS[hash as usize % GRADIENTS.len()]
+    }
+}
+
+impl HeightGrid {
+    fn step(&self) -> f32 {
+        self.size / self.resolution as f32
+    }
+
+    /// Height of the vertex at `column` and `row`, -1 and `resolution + 1` are the extra samples.
+    fn height(&self, column: i32, row: i32) -> f32 {
+        let samples = self.resolution + 3;
+        self.heights[(row + 1) as usize * samples + (column + 1) as usize]
+    }
+
+    fn mesh(&self) -> Mesh {
+        let vertices = self.resolution as i32 + 1;
+        let step = self.step();
+        let half_size = self.size / 2.0;
+
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        for row in 0..vertices {
+            for column in 0..vertices {
+                positions.push([column as f32 * step - half_size, self.height(column, row), row as f32 * step - half_size]);
+                let normal = Vec3::new(
+                    self.height(column - 1, row) - self.height(column + 1, row),
+                    2.0 * step,
+                    self.height(column, row - 1) - self.height(column, row + 1),
+                );
+                normals.push(normal.normalize().to_array());
+                uvs.push([column as f32 / self.resolution as f32, row as f32 / self.resolution as f32]);
+            }
+        }
+
+        // Two counter clockwise triangles per cell, seen from above.
+        let vertices = vertices as u32;
+        let mut indices = Vec::new();
+        for row in 0..self.resolution as u32 {
+            for column in 0..self.resolution as u32 {
+                let corner = row * vertices + column;
+                indices.extend([corner, corner + vertices, corner + 1]);
+                indices.extend([corner + 1, corner + vertices, corner + vertices + 1]);
+            }
+        }
+
+        Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_indices(Indices::U32(indices))
+    }
+
+    fn heightfield(&self) -> TerrainHeightfield {
+        let vertices = self.resolution as i32 + 1;
+        let heights = (0..vertices)
+            .flat_map(|row| (0..vertices).map(move |column| (column, row)))
+            .map(|(column, row)| self.height(column, row))
+            .collect();
+
+        TerrainHeightfield {
+            heights,
+            rows: vertices as usize,
+            columns: vertices as usize,
+            size: Vec2::splat(self.size),
+        }
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if the terrain only depends on the seed, the edges of
+    /// neighbour chunks match and the heightfield has the heights of the finest mesh.
+    #[test]
+    fn test_procedural_terrain() {
+        let terrain = ProceduralTerrain::new(42);
+        assert_eq!(terrain, ProceduralTerrain::new(42));
+        assert_ne!(terrain, ProceduralTerrain::new(43));
+        assert_eq!(terrain.height(Vec2::ZERO), 0.0);
+
+        let left = terrain.grid(0, 0, 512, 64);
+        let right = terrain.grid(512, 0, 512, 64);
+        for row in 0..=64 {
+            assert_eq!(left.height(64, row), right.height(0, row));
+        }
+        assert!(left.heights.iter().any(|height| height.abs() > 1.0));
+
+        let heightfield = left.heightfield();
+        assert_eq!((heightfield.rows, heightfield.columns), (65, 65));
+        let mesh = left.mesh();
+        let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap().as_float3().unwrap();
+        assert_eq!(positions.len(), heightfield.heights.len());
+        assert!(positions.iter().zip(heightfield.heights.iter()).all(|(position, height)| position[1] == *height));
+        assert_eq!(positions[1], [-248.0, heightfield.heights[1], -256.0]);
+        assert_eq!(mesh.indices().unwrap().len(), 64 * 64 * 6);
+    }
+}
@@ -0,0 +1,140 @@
+use std::time::Duration;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use clap::ValueEnum;
+use crate::entities::player::Player;
+use crate::environment::{ChunkManager, FloatingOrigin};
+use crate::logic::{save_recording_on_exit, LoadingState};
+
+/// Default simulation rate of the headless mode in ticks per second.
+pub const DEFAULT_TICK_RATE: f64 = 60.0;
+
+//################################################# Models #################################################
+/// Condition which stops the headless run early with [`AppExit::Success`].
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeadlessExit {
+    /// All chunks around the player are streamed in.
+    AreaReady,
+}
+
+/// Configuration of the headless run. Without exit condition the run stops
+/// successfully after `max_ticks`, with one it fails if the condition is not
+/// reached in `max_ticks`.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct HeadlessConfig {
+    pub tick_rate: f64,
+    pub max_ticks: Option<u64>,
+    pub exit_condition: Option<HeadlessExit>,
+}
+
+/// Simulated ticks since the start.
+#[derive(Resource, Debug, Default)]
+pub struct HeadlessTicks(pub u64);
+
+//################################################# Implementations #################################################
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        Self {
+            tick_rate: DEFAULT_TICK_RATE,
+            max_ticks: None,
+            exit_condition: None,
+        }
+    }
+}
+
+impl HeadlessConfig {
+    pub fn tick(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.tick_rate)
+    }
+}
+
+/// Run the simulation with a fixed tick. Every update advances the time by
+/// exactly one tick, independent of the wall clock, so runs are repeatable.
+pub struct HeadlessPlugin {
+    pub config: HeadlessConfig,
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        let tick = self.config.tick();
+
+        app.insert_resource(self.config.clone())
+            .init_resource::<HeadlessTicks>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(tick))
+            .insert_resource(Time::<Fixed>::from_duration(tick));
+
+        // The exit is sent before the input recording is saved, so a recorded headless run is complete.
+        app.add_systems(Last, check_headless_exit.before(save_recording_on_exit));
+
+        info!("Headless run with {} ticks per second, max ticks {:?}, exit condition {:?}",
+            self.config.tick_rate, self.config.max_ticks, self.config.exit_condition);
+    }
+}
+
+fn check_headless_exit(config: Res<HeadlessConfig>,
+                       mut ticks: ResMut<HeadlessTicks>,
+                       mut exit_event_writer: EventWriter<AppExit>,
+                       chunk_manager: Res<ChunkManager>,
+                       origin: Res<FloatingOrigin>,
+                       loading_state: Res<LoadingState>,
+                       player_query: Query<&Transform, With<Player>>
+) {
+    ticks.0 += 1;
+
+    if *loading_state == LoadingState::Failed {
+        error!("Headless run failed to load the assets after {} ticks", ticks.0);
+        exit_event_writer.send(AppExit::error());
+        return;
+    }
+
+    if let Some(condition) = config.exit_condition {
+        let reached = match condition {
+            HeadlessExit::AreaReady => player_query.get_single()
+                .map_or(false, |transform| chunk_manager.is_area_ready(origin.to_world(transform.translation))),
+        };
+
+        if reached {
+            info!("Headless exit condition {:?} reached after {} ticks", condition, ticks.0);
+            exit_event_writer.send(AppExit::Success);
+            return;
+        }
+    }
+
+    if config.max_ticks.is_some_and(|max_ticks| ticks.0 >= max_ticks) {
+        if let Some(condition) = config.exit_condition {
+            error!("Headless exit condition {:?} not reached in {} ticks", condition, ticks.0);
+            exit_event_writer.send(AppExit::error());
+        } else {
+            info!("Headless run finished after {} ticks", ticks.0);
+            exit_event_writer.send(AppExit::Success);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if the run fails if the exit condition is not reached in time.
+    #[test]
+    fn test_headless_exit_after_ticks() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.init_resource::<ChunkManager>()
+            .init_resource::<LoadingState>();
+        app.add_plugins(HeadlessPlugin {
+            config: HeadlessConfig {
+                max_ticks: Some(3),
+                exit_condition: Some(HeadlessExit::AreaReady),
+                ..default()
+            },
+        });
+
+        app.update();
+        app.update();
+        assert!(app.should_exit().is_none());
+
+        app.update();
+        assert_eq!(app.should_exit(), Some(AppExit::error()));
+    }
+}
@@ -0,0 +1,122 @@
+use bevy::asset::{AssetPath, LoadState, UntypedAssetLoadFailedEvent};
+use bevy::prelude::*;
+#[cfg(debug_assertions)]
+use crate::manager::RunMode;
+
+//################################################# Models #################################################
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetFailure {
+    pub path: String,
+    pub error: String,
+}
+
+/// Every asset which failed to load since the start. Systems with a
+/// fallback asset check their handles with [`AssetFailures::is_failed`].
+#[derive(Resource, Debug, Default)]
+pub struct AssetFailures {
+    pub failures: Vec<AssetFailure>,
+}
+
+/// Text of the dev build overlay which lists the failed assets.
+#[cfg(debug_assertions)]
+#[derive(Component, Debug)]
+pub struct AssetFailureOverlay;
+
+//################################################# Implementations #################################################
+impl AssetFailures {
+    /// True if the asset of `handle` or the file of a labeled sub asset failed.
+    pub fn is_failed<A: Asset>(&self, asset_server: &AssetServer, handle: &Handle<A>) -> bool {
+        if matches!(asset_server.get_load_state(handle), Some(LoadState::Failed(_))) {
+            return true;
+        }
+
+        handle.path().map_or(false, |path| self.contains_file(path))
+    }
+
+    /// True if the file of `path` failed, the label is ignored.
+    pub fn contains_file(&self, path: &AssetPath) -> bool {
+        let file = path.without_label().to_string();
+        self.failures.iter().any(|failure| failure.path == file)
+    }
+}
+
+pub struct AssetFailuresPlugin;
+
+impl Plugin for AssetFailuresPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AssetFailures>();
+
+        app.add_systems(PreUpdate, collect_asset_failures);
+
+        #[cfg(debug_assertions)]
+        if RunMode::of(app) == RunMode::Windowed {
+            app.add_systems(Startup, spawn_asset_failure_overlay);
+            app.add_systems(Update, update_asset_failure_overlay.run_if(resource_changed::<AssetFailures>));
+        }
+    }
+}
+
+fn collect_asset_failures(mut failed_event_reader: EventReader<UntypedAssetLoadFailedEvent>,
+                          mut asset_failures: ResMut<AssetFailures>
+) {
+    for event in failed_event_reader.read() {
+        error!("Failed to load asset {}: {}", event.path, event.error);
+        asset_failures.failures.push(AssetFailure {
+            path: event.path.without_label().to_string(),
+            error: event.error.to_string(),
+        });
+    }
+}
+
+#[cfg(debug_assertions)]
+fn spawn_asset_failure_overlay(mut commands: Commands) {
+    let mut overlay = TextBundle::from_section("", TextStyle {
+        font_size: 16.0,
+        color: Color::srgb(1.0, 0.35, 0.3),
+        ..default()
+    }).with_style(Style {
+        position_type: PositionType::Absolute,
+        top: Val::Px(8.0),
+        left: Val::Px(8.0),
+        ..default()
+    }).with_background_color(Color::srgba(0.0, 0.0, 0.0, 0.7));
+    // Stay above the loading screen.
+    overlay.z_index = ZIndex::Global(i32::MAX);
+    overlay.visibility = Visibility::Hidden;
+
+    commands.spawn((Name::new("AssetFailureOverlay"), AssetFailureOverlay, overlay));
+}
+
+#[cfg(debug_assertions)]
+fn update_asset_failure_overlay(asset_failures: Res<AssetFailures>,
+                                mut overlay_query: Query<(&mut Text, &mut Visibility), With<AssetFailureOverlay>>
+) {
+    for (mut text, mut visibility) in overlay_query.iter_mut() {
+        text.sections[0].value = asset_failures.failures.iter()
+            .map(|failure| format!("Failed to load {}: {}", failure.path, failure.error))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        *visibility = if asset_failures.failures.is_empty() { Visibility::Hidden } else { Visibility::Visible };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if a labeled sub asset counts as failed if his file failed.
+    #[test]
+    fn test_labeled_asset_is_failed() {
+        let asset_failures = AssetFailures {
+            failures: vec![AssetFailure {
+                path: "entities/player.glb".to_string(),
+                error: "file not found".to_string(),
+            }],
+        };
+
+        assert!(asset_failures.contains_file(&AssetPath::parse("entities/player.glb#Scene0")));
+        assert!(asset_failures.contains_file(&AssetPath::parse("entities/player.glb")));
+        assert!(!asset_failures.contains_file(&AssetPath::parse("maps/debug.glb")));
+    }
+}
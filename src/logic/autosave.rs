@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+use crate::entities::BossKilled;
+use crate::environment::{CheckpointRested, ChunkEntered};
+use crate::logic::save_game::{LoadRequest, SaveRequest};
+
+/// Slot which is overwritten by every autosave.
+pub const AUTOSAVE_SLOT: u8 = 0;
+/// Slot of the quicksave debug hotkeys.
+pub const QUICKSAVE_SLOT: u8 = 9;
+/// Time in seconds between two timer autosaves.
+const AUTOSAVE_INTERVAL: f32 = 300.0;
+
+#[derive(Resource, Debug)]
+pub struct AutosaveTimer(pub Timer);
+
+impl Default for AutosaveTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(AUTOSAVE_INTERVAL, TimerMode::Repeating))
+    }
+}
+
+pub struct AutosavePlugin;
+
+impl Plugin for AutosavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AutosaveTimer>();
+
+        app.add_systems(Update, (
+            autosave_on_timer,
+            autosave_on_checkpoint_rest,
+            autosave_on_area_transition,
+            autosave_on_boss_kill
+        ));
+
+        #[cfg(debug_assertions)]
+        app.add_systems(Update, handle_quicksave_keys);
+    }
+}
+
+fn autosave_on_timer(time: Res<Time>,
+                     mut autosave_timer: ResMut<AutosaveTimer>,
+                     mut save_event_writer: EventWriter<SaveRequest>
+) {
+    if autosave_timer.0.tick(time.delta()).just_finished() {
+        save_event_writer.send(SaveRequest { slot: AUTOSAVE_SLOT });
+    }
+}
+
+fn autosave_on_checkpoint_rest(mut rested_event_reader: EventReader<CheckpointRested>,
+                               mut autosave_timer: ResMut<AutosaveTimer>,
+                               mut save_event_writer: EventWriter<SaveRequest>
+) {
+    for event in rested_event_reader.read() {
+        info!("Autosave after rest at {:?}", event.id);
+        autosave_timer.0.reset();
+        save_event_writer.send(SaveRequest { slot: AUTOSAVE_SLOT });
+    }
+}
+
+fn autosave_on_area_transition(mut current_area: Local<Option<String>>,
+                               mut entered_event_reader: EventReader<ChunkEntered>,
+                               mut autosave_timer: ResMut<AutosaveTimer>,
+                               mut save_event_writer: EventWriter<SaveRequest>
+) {
+    for event in entered_event_reader.read() {
+        if current_area.as_ref() == Some(&event.area) {
+            continue;
+        }
+
+        // The first entered area is the start area and no transition.
+        if current_area.is_some() {
+            info!("Autosave after area transition to {:?}", event.area);
+            autosave_timer.0.reset();
+            save_event_writer.send(SaveRequest { slot: AUTOSAVE_SLOT });
+        }
+        *current_area = Some(event.area.clone());
+    }
+}
+
+fn autosave_on_boss_kill(mut boss_event_reader: EventReader<BossKilled>,
+                         mut autosave_timer: ResMut<AutosaveTimer>,
+                         mut save_event_writer: EventWriter<SaveRequest>
+) {
+    for event in boss_event_reader.read() {
+        info!("Autosave after boss kill {:?}", event.id);
+        autosave_timer.0.reset();
+        save_event_writer.send(SaveRequest { slot: AUTOSAVE_SLOT });
+    }
+}
+
+#[cfg(debug_assertions)]
+fn handle_quicksave_keys(keyboard: Res<ButtonInput<KeyCode>>,
+                         mut save_event_writer: EventWriter<SaveRequest>,
+                         mut load_event_writer: EventWriter<LoadRequest>
+) {
+    let quicksave_key = KeyCode::F5;
+    let quickload_key = KeyCode::F9;
+
+    if keyboard.just_pressed(quicksave_key) {
+        save_event_writer.send(SaveRequest { slot: QUICKSAVE_SLOT });
+    }
+
+    if keyboard.just_pressed(quickload_key) {
+        load_event_writer.send(LoadRequest { slot: QUICKSAVE_SLOT });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use bevy::time::TimeUpdateStrategy;
+
+    /// Unit Test for check if the timer sends an autosave request after the interval.
+    #[test]
+    fn test_autosave_on_timer() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_event::<SaveRequest>();
+        app.init_resource::<AutosaveTimer>();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(AUTOSAVE_INTERVAL / 2.0 + 1.0)));
+        app.add_systems(Update, autosave_on_timer);
+        // Virtual time clamps every frame to his max delta, the steps are longer.
+        app.world_mut().resource_mut::<Time<Virtual>>().set_max_delta(Duration::from_secs_f32(AUTOSAVE_INTERVAL));
+
+        app.update();
+        app.update();
+        app.update();
+
+        let events = app.world().resource::<Events<SaveRequest>>();
+        let slots: Vec<u8> = events.get_reader().read(events).map(|event| event.slot).collect();
+        assert_eq!(slots, vec![AUTOSAVE_SLOT]);
+    }
+}
@@ -0,0 +1,153 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use bevy::core::FrameCount;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+//################################################# Models #################################################
+/// Keyboard input of a run, stored as TOML. Every entry is a key change at
+/// the frame `tick`, so the replay is exact with the fixed tick of the
+/// headless mode.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct InputRecording {
+    pub events: Vec<RecordedInput>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RecordedInput {
+    pub tick: u32,
+    pub key: KeyCode,
+    pub pressed: bool,
+}
+
+/// Write the keyboard input into `path` if the app exits.
+pub struct InputRecordPlugin {
+    pub path: PathBuf,
+}
+
+/// Replay a recording made by [`InputRecordPlugin`].
+pub struct InputReplayPlugin {
+    pub recording: InputRecording,
+}
+
+#[derive(Resource, Debug, Clone)]
+struct RecordingFile(PathBuf);
+
+/// Next entry of the replay which was not pressed or released yet.
+#[derive(Resource, Debug, Default)]
+struct ReplayCursor(usize);
+
+//################################################# Implementations #################################################
+impl InputRecording {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut recording: Self = toml::from_str(&fs::read_to_string(path)?)?;
+        recording.events.sort_by_key(|event| event.tick);
+        Ok(recording)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+impl Plugin for InputRecordPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputRecording>()
+            .insert_resource(RecordingFile(self.path.clone()));
+
+        app.add_systems(PreUpdate, record_input.after(InputSystem));
+        app.add_systems(Last, save_recording_on_exit);
+    }
+}
+
+impl Plugin for InputReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.recording.clone())
+            .init_resource::<ReplayCursor>();
+
+        app.add_systems(PreUpdate, replay_input.after(InputSystem));
+    }
+}
+
+fn record_input(frame_count: Res<FrameCount>,
+                keyboard: Res<ButtonInput<KeyCode>>,
+                mut recording: ResMut<InputRecording>
+) {
+    for key in keyboard.get_just_pressed() {
+        recording.events.push(RecordedInput { tick: frame_count.0, key: *key, pressed: true });
+    }
+
+    for key in keyboard.get_just_released() {
+        recording.events.push(RecordedInput { tick: frame_count.0, key: *key, pressed: false });
+    }
+}
+
+pub(crate) fn save_recording_on_exit(mut exit_event_reader: EventReader<AppExit>,
+                                     recording: Res<InputRecording>,
+                                     recording_file: Res<RecordingFile>
+) {
+    if exit_event_reader.read().last().is_none() {
+        return;
+    }
+
+    match recording.save(&recording_file.0) {
+        Ok(()) => info!("Saved input recording with {} events to {:?}", recording.events.len(), recording_file.0),
+        Err(error) => error!("Failed to save input recording to {:?}: {}", recording_file.0, error),
+    }
+}
+
+/// Press and release the recorded keys, runs after the [`InputSystem`] so
+/// the changes are not cleared before the game reads them.
+fn replay_input(frame_count: Res<FrameCount>,
+                recording: Res<InputRecording>,
+                mut cursor: ResMut<ReplayCursor>,
+                mut keyboard: ResMut<ButtonInput<KeyCode>>
+) {
+    while let Some(event) = recording.events.get(cursor.0) {
+        if event.tick > frame_count.0 {
+            break;
+        }
+
+        if event.pressed {
+            keyboard.press(event.key);
+        } else {
+            keyboard.release(event.key);
+        }
+        cursor.0 += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::input::InputPlugin;
+
+    /// Unit Test for check if the replay presses and releases the keys at the recorded ticks.
+    #[test]
+    fn test_replay_input() {
+        let recording = InputRecording {
+            events: vec![
+                RecordedInput { tick: 1, key: KeyCode::KeyW, pressed: true },
+                RecordedInput { tick: 3, key: KeyCode::KeyW, pressed: false },
+            ],
+        };
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, InputPlugin));
+        app.add_plugins(InputReplayPlugin { recording });
+
+        app.update();
+        assert!(!app.world().resource::<ButtonInput<KeyCode>>().pressed(KeyCode::KeyW));
+
+        app.update();
+        assert!(app.world().resource::<ButtonInput<KeyCode>>().just_pressed(KeyCode::KeyW));
+
+        app.update();
+        assert!(app.world().resource::<ButtonInput<KeyCode>>().pressed(KeyCode::KeyW));
+
+        app.update();
+        assert!(app.world().resource::<ButtonInput<KeyCode>>().just_released(KeyCode::KeyW));
+    }
+}
@@ -1,17 +1,32 @@
 use bevy::asset::RecursiveDependencyLoadState;
 use bevy::prelude::*;
-use crate::logic::loading_handler::pipeline_check::{PipelineCheckPlugin, PipelinesReady};
+use crate::logic::loading_handler::pipeline_check::PipelineCheckPlugin;
 
-#[derive(Resource, Default)]
+pub use crate::logic::loading_handler::pipeline_check::PipelinesReady;
+
+#[derive(Resource, Default, Debug, Clone, PartialEq, Eq)]
 pub enum LoadingState {
-    Loading,
+    /// Start state, the loading screen stays until the first check is done.
     #[default]
-    Ready
+    Loading,
+    Ready,
+    /// Everything is done, but at least one registered asset or one of his
+    /// dependencies failed. The game continues with the fallback assets.
+    Failed,
 }
 
+/// Assets the loading screen waits for. Register a handle with
+/// [`LoadingData::register`] and the loading screen stays until the asset
+/// and all his dependencies are loaded.
 #[derive(Resource, Default, Debug)]
 pub struct LoadingData {
     pub assets: Vec<UntypedHandle>,
+    /// Count of all registered assets, used for the progress.
+    pub total: usize,
+    /// Paths of the assets which failed to load.
+    pub failed: Vec<String>,
+    /// Highest count of waiting pipelines, used for the progress.
+    pub max_waiting_pipelines: usize,
     pub confirmation_frames_target: usize,
     pub confirmation_frames_count: usize,
 }
@@ -19,11 +34,30 @@ pub struct LoadingData {
 impl LoadingData {
     pub fn new(confirmation_frames_target: usize) -> Self {
         Self {
-            assets: Vec::new(),
             confirmation_frames_target,
-            confirmation_frames_count: 0,
+            ..default()
         }
     }
+
+    pub fn register(&mut self, handle: impl Into<UntypedHandle>) {
+        self.assets.push(handle.into());
+        self.total += 1;
+    }
+
+    /// Loaded part from `0.0` up to `1.0` of the registered handles and the
+    /// pipelines which were waiting for compilation. Every handle is one step,
+    /// done once he and all his dependencies are loaded. The dependencies have
+    /// no own step, the asset server does not list them for assets like a glb.
+    pub fn handle_progress(&self, pipelines_ready: &PipelinesReady) -> f32 {
+        let total = self.total + self.max_waiting_pipelines;
+        if total == 0 {
+            return if pipelines_ready.ready { 1.0 } else { 0.0 };
+        }
+
+        let loaded_assets = self.total - self.assets.len();
+        let compiled_pipelines = self.max_waiting_pipelines - pipelines_ready.waiting.min(self.max_waiting_pipelines);
+        (loaded_assets + compiled_pipelines) as f32 / total as f32
+    }
 }
 
 pub struct LoadingHandlerPlugin;
@@ -31,7 +65,7 @@ pub struct LoadingHandlerPlugin;
 impl Plugin for LoadingHandlerPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(LoadingData::new(5))
-            .insert_resource(LoadingState::default());
+            .insert_resource(LoadingState::Loading);
 
         app.add_systems(Update, update_pipeline_loading_state);
 
@@ -39,55 +73,108 @@ impl Plugin for LoadingHandlerPlugin {
     }
 }
 
-fn update_pipeline_loading_state(mut loading_data: ResMut<LoadingData>,
-                                 mut loading_state: ResMut<LoadingState>,
-                                 asset_server: Res<AssetServer>,
-                                 pipelines_ready: Res<PipelinesReady>) {
-    if !loading_data.assets.is_empty() || !pipelines_ready.0 {
-        loading_data.confirmation_frames_count = 0;
-
-        let mut pop_list: Vec<usize> = Vec::new();
-        for (index, asset) in loading_data.assets.iter().enumerate() {
-            if let Some(state) = asset_server.get_recursive_dependency_load_state(asset) {
-                if let RecursiveDependencyLoadState::Loaded = state {
-                    pop_list.push(index);
-                }
-            }
+pub(crate) fn update_pipeline_loading_state(mut loading_data: ResMut<LoadingData>,
+                                            mut loading_state: ResMut<LoadingState>,
+                                            asset_server: Res<AssetServer>,
+                                            pipelines_ready: Res<PipelinesReady>) {
+    let LoadingData { assets, failed, .. } = &mut *loading_data;
+    assets.retain(|asset| match asset_server.get_recursive_dependency_load_state(asset) {
+        Some(RecursiveDependencyLoadState::Loaded) => false,
+        Some(RecursiveDependencyLoadState::Failed) => {
+            let path = asset.path().map_or_else(|| format!("{:?}", asset.id()), |path| path.to_string());
+            error!("Failed to load asset {} or one of his dependencies", path);
+            failed.push(path);
+            false
         }
+        _ => true,
+    });
 
-        for i in pop_list.iter() {
-            loading_data.assets.remove(*i);
-        }
-    } else {
+    loading_data.max_waiting_pipelines = loading_data.max_waiting_pipelines.max(pipelines_ready.waiting);
+
+    if !loading_data.assets.is_empty() || !pipelines_ready.ready {
+        loading_data.confirmation_frames_count = 0;
+        loading_state.set_if_neq(LoadingState::Loading);
+    } else if !loading_data.failed.is_empty() {
+        loading_state.set_if_neq(LoadingState::Failed);
+    } else if loading_data.confirmation_frames_count < loading_data.confirmation_frames_target {
         loading_data.confirmation_frames_count += 1;
-        if loading_data.confirmation_frames_count == loading_data.confirmation_frames_target {
-            *loading_state = LoadingState::Ready;
-            info!("Loading asset: {}", loading_data.assets.len());
-        } else {
-            *loading_state = LoadingState::Loading;
-        }
+        loading_state.set_if_neq(LoadingState::Loading);
+    } else if loading_state.set_if_neq(LoadingState::Ready) {
+        info!("Loading finished with {} assets", loading_data.total);
     }
 }
 
 mod pipeline_check {
     use bevy::{prelude::*, render::render_resource::*, render::*};
 
+    /// State of the render pipelines, `waiting` is the count of pipelines
+    /// which are not compiled yet.
     #[derive(Resource, Default, Debug)]
-    pub struct PipelinesReady(pub bool);
+    pub struct PipelinesReady {
+        pub ready: bool,
+        pub waiting: usize,
+    }
 
     pub struct PipelineCheckPlugin;
 
     impl Plugin for PipelineCheckPlugin {
         fn build(&self, app: &mut App) {
+            // Without renderer there are no pipelines to wait for.
+            let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+                app.insert_resource(PipelinesReady { ready: true, waiting: 0 });
+                return;
+            };
+
+            render_app.add_systems(ExtractSchedule, update_pipeline_checks);
             app.insert_resource(PipelinesReady::default());
-            app.sub_app_mut(RenderApp)
-                .add_systems(ExtractSchedule, update_pipeline_checks);
         }
     }
 
     fn update_pipeline_checks(mut main_world: ResMut<MainWorld>, pipelines: Res<PipelineCache>) {
-        if let Some(mut ready) = main_world.get_resource_mut::<PipelinesReady>() {
-            ready.0 = pipelines.waiting_pipelines().count() == 0;
+        if let Some(mut pipelines_ready) = main_world.get_resource_mut::<PipelinesReady>() {
+            pipelines_ready.waiting = pipelines.waiting_pipelines().count();
+            pipelines_ready.ready = pipelines_ready.waiting == 0;
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if the progress counts loaded handles and compiled pipelines.
+    #[test]
+    fn test_loading_progress() {
+        let mut loading_data = LoadingData::new(5);
+        loading_data.register(Handle::<Image>::default());
+        loading_data.register(Handle::<Image>::default());
+        loading_data.assets.pop();
+        loading_data.max_waiting_pipelines = 2;
+
+        let progress = loading_data.handle_progress(&PipelinesReady { ready: false, waiting: 1 });
+        assert_eq!(progress, 0.5);
+
+        loading_data.assets.clear();
+        let progress = loading_data.handle_progress(&PipelinesReady { ready: true, waiting: 0 });
+        assert_eq!(progress, 1.0);
+    }
+
+    /// Unit Test for check if the state gets ready after the confirmation frames and stays ready.
+    #[test]
+    fn test_loading_state_ready_after_confirmation() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()));
+        app.insert_resource(LoadingData::new(2))
+            .insert_resource(LoadingState::Loading)
+            .insert_resource(PipelinesReady { ready: true, waiting: 0 });
+        app.add_systems(Update, update_pipeline_loading_state);
+
+        app.update();
+        app.update();
+        assert_eq!(*app.world().resource::<LoadingState>(), LoadingState::Loading);
+
+        app.update();
+        app.update();
+        assert_eq!(*app.world().resource::<LoadingState>(), LoadingState::Ready);
+    }
+}
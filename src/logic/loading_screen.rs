@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+use crate::logic::loading_handler::{update_pipeline_loading_state, LoadingData, LoadingState, PipelinesReady};
+use crate::manager::{AppState, InGameState, RunMode};
+
+/// Width of the progress bar in pixels.
+const PROGRESS_BAR_WIDTH: f32 = 480.0;
+
+#[derive(Component, Debug)]
+pub struct LoadingScreen;
+
+#[derive(Component, Debug)]
+pub struct LoadingProgressBar;
+
+#[derive(Component, Debug)]
+pub struct LoadingText;
+
+/// Shows the progress of the [`LoadingData`] in [`AppState::WaitingScreen`]
+/// and starts the game if everything is loaded.
+pub struct LoadingScreenPlugin;
+
+impl Plugin for LoadingScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, finish_loading
+            .after(update_pipeline_loading_state)
+            .run_if(in_state(AppState::WaitingScreen)));
+
+        if RunMode::of(app) == RunMode::Windowed {
+            app.add_systems(OnEnter(AppState::WaitingScreen), spawn_loading_screen);
+            app.add_systems(Update, update_loading_screen.run_if(in_state(AppState::WaitingScreen)));
+            app.add_systems(OnExit(AppState::WaitingScreen), despawn_loading_screen);
+        }
+    }
+}
+
+fn spawn_loading_screen(mut commands: Commands) {
+    commands.spawn((
+        Name::new("LoadingScreen"),
+        LoadingScreen,
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(16.0),
+                ..default()
+            },
+            background_color: BackgroundColor(Color::srgb(0.05, 0.05, 0.07)),
+            // Stay above the game camera and the ui of the game.
+            z_index: ZIndex::Global(i32::MAX),
+            ..default()
+        },
+    )).with_children(|parent| {
+        parent.spawn((
+            LoadingText,
+            TextBundle::from_section("Loading 0%", TextStyle {
+                font_size: 28.0,
+                color: Color::WHITE,
+                ..default()
+            }),
+        ));
+
+        parent.spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(PROGRESS_BAR_WIDTH),
+                height: Val::Px(12.0),
+                ..default()
+            },
+            background_color: BackgroundColor(Color::srgb(0.2, 0.2, 0.25)),
+            ..default()
+        }).with_children(|bar| {
+            bar.spawn((
+                LoadingProgressBar,
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(Color::srgb(0.85, 0.75, 0.45)),
+                    ..default()
+                },
+            ));
+        });
+    });
+}
+
+fn update_loading_screen(loading_data: Res<LoadingData>,
+                         loading_state: Res<LoadingState>,
+                         pipelines_ready: Res<PipelinesReady>,
+                         mut bar_query: Query<&mut Style, With<LoadingProgressBar>>,
+                         mut text_query: Query<&mut Text, With<LoadingText>>
+) {
+    let progress = loading_data.handle_progress(&pipelines_ready);
+
+    for mut style in bar_query.iter_mut() {
+        style.width = Val::Percent(progress * 100.0);
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = match *loading_state {
+            LoadingState::Failed => format!("Failed to load {}", loading_data.failed.join(", ")),
+            _ => format!("Loading {:.0}%", progress * 100.0),
+        };
+    }
+}
+
+fn despawn_loading_screen(mut commands: Commands, screen_query: Query<Entity, With<LoadingScreen>>) {
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Failed assets do not block the game, the systems with fallback assets
+/// replace them and the dev overlay lists the failures.
+fn finish_loading(loading_state: Res<LoadingState>, mut next_state: ResMut<NextState<AppState>>) {
+    if matches!(*loading_state, LoadingState::Ready | LoadingState::Failed) {
+        next_state.set(AppState::InGame(InGameState::default()));
+    }
+}
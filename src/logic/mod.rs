@@ -1,12 +1,32 @@
+mod asset_failures;
+mod autosave;
+mod input_recording;
 mod loading_handler;
+mod loading_screen;
+mod save_game;
+mod settings;
+
+pub use asset_failures::AssetFailures;
+#[cfg(test)]
+pub use asset_failures::AssetFailure;
+pub use input_recording::{InputRecordPlugin, InputRecording, InputReplayPlugin};
+pub(crate) use input_recording::save_recording_on_exit;
+pub use loading_handler::{LoadingData, LoadingState};
+pub use save_game::LoadRequest;
+pub use settings::{GameSettings, RenderBackend, SettingsFile};
 
 use bevy::prelude::*;
+use crate::logic::asset_failures::AssetFailuresPlugin;
+use crate::logic::autosave::AutosavePlugin;
 use crate::logic::loading_handler::LoadingHandlerPlugin;
+use crate::logic::loading_screen::LoadingScreenPlugin;
+use crate::logic::save_game::SaveGamePlugin;
+use crate::logic::settings::SettingsPlugin;
 
 pub struct LogicPlugin;
 
 impl Plugin for LogicPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(LoadingHandlerPlugin);
+        app.add_plugins((AssetFailuresPlugin, LoadingHandlerPlugin, LoadingScreenPlugin, SaveGamePlugin, AutosavePlugin, SettingsPlugin));
     }
 }
\ No newline at end of file
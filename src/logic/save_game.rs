@@ -0,0 +1,578 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use bevy::prelude::*;
+use bevy::tasks::{IoTaskPool, Task};
+use bevy::tasks::futures_lite::future;
+use serde::{Deserialize, Serialize};
+use crate::entities::player::{Climber, DeathSequence, FallTracker, Inventory, Player, PlayerSkillAbleStats, PlayerState, Stance, STANDING_HALF_EXTENTS};
+use crate::environment::{ChunkManager, FloatingOrigin, GlobalPosition, LastCheckpoint, WorldFlags};
+use crate::physics::{player_collider, set_body_kinematic, BodyVelocity};
+
+/// Current version of the save file layout. Increase it on every breaking
+/// change and add a migration to [`MIGRATIONS`].
+pub const SAVE_VERSION: u32 = 2;
+
+/// Migration steps, the entry at index `n` migrates a save from version
+/// `n + 1` to `n + 2`.
+const MIGRATIONS: &[fn(&mut toml::Table)] = &[migrate_global_position];
+
+//################################################# Models #################################################
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SaveGame {
+    pub version: u32,
+    pub player: PlayerSave,
+    pub world: WorldSave,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlayerSave {
+    /// Translation relative to the [`FloatingOrigin`] of the save, the
+    /// player is placed by `position`.
+    pub transform: Transform,
+    pub position: GlobalPosition,
+    pub player: Player,
+    pub skills: PlayerSkillAbleStats,
+    pub inventory: Inventory,
+}
+
+/// World part of the save. The `area` is the area of the chunk the player
+/// stood in, a save is only loaded in the same area.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WorldSave {
+    /// Empty for saves written before the area was stored.
+    #[serde(default)]
+    pub area: String,
+    pub checkpoint_id: Option<String>,
+    pub checkpoint_position: Option<Vec3>,
+    pub flags: WorldFlags,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Serialize(toml::ser::Error),
+    Deserialize(toml::de::Error),
+    UnsupportedVersion(u32),
+}
+
+/// Directory of the save slots. Every slot is stored as `slot_<n>.toml`
+/// next to his backup `slot_<n>.toml.bak`.
+#[derive(Resource, Debug, Clone)]
+pub struct SaveSlots {
+    pub directory: PathBuf,
+}
+
+/// Requested save slots and the running write tasks. A request waits in the
+/// queue while the player is in a state without consistent snapshot.
+#[derive(Resource, Default)]
+pub struct SaveQueue {
+    pub requested: Vec<u8>,
+    pub in_flight: Vec<(u8, Task<Result<(), SaveError>>)>,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SaveRequest {
+    pub slot: u8,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct LoadRequest {
+    pub slot: u8,
+}
+
+//################################################# Implementations #################################################
+impl Default for SaveSlots {
+    fn default() -> Self {
+        let directory = dirs::data_dir()
+            .map(|directory| directory.join("mira-game").join("saves"))
+            .unwrap_or_else(|| PathBuf::from("saves"));
+
+        Self { directory }
+    }
+}
+
+impl SaveSlots {
+    pub fn slot_path(&self, slot: u8) -> PathBuf {
+        self.directory.join(format!("slot_{}.toml", slot))
+    }
+}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "save file io error: {}", error),
+            SaveError::Serialize(error) => write!(f, "save file serialize error: {}", error),
+            SaveError::Deserialize(error) => write!(f, "save file is corrupted: {}", error),
+            SaveError::UnsupportedVersion(version) => write!(f, "save file version {} is newer than {}", version, SAVE_VERSION),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<std::io::Error> for SaveError {
+    fn from(error: std::io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+
+impl From<toml::ser::Error> for SaveError {
+    fn from(error: toml::ser::Error) -> Self {
+        SaveError::Serialize(error)
+    }
+}
+
+impl From<toml::de::Error> for SaveError {
+    fn from(error: toml::de::Error) -> Self {
+        SaveError::Deserialize(error)
+    }
+}
+
+impl SaveQueue {
+    pub fn is_saving(&self, slot: u8) -> bool {
+        self.in_flight.iter().any(|(in_flight_slot, _)| *in_flight_slot == slot)
+    }
+}
+
+impl SaveGame {
+    pub fn to_toml(&self) -> Result<String, SaveError> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    /// Parse a save file and migrate it up to [`SAVE_VERSION`].
+    pub fn from_toml(content: &str) -> Result<Self, SaveError> {
+        let mut table: toml::Table = toml::from_str(content)?;
+        let version = table.get("version")
+            .and_then(|version| version.as_integer())
+            .unwrap_or(1) as u32;
+
+        if version > SAVE_VERSION {
+            return Err(SaveError::UnsupportedVersion(version));
+        }
+
+        for migration in MIGRATIONS.iter().skip(version.saturating_sub(1) as usize) {
+            migration(&mut table);
+        }
+        table.insert("version".to_string(), toml::Value::Integer(SAVE_VERSION as i64));
+
+        Ok(toml::Value::Table(table).try_into()?)
+    }
+}
+
+/// Version 1 had no floating origin, so the translation is the world position.
+fn migrate_global_position(table: &mut toml::Table) {
+    let Some(player) = table.get_mut("player").and_then(|player| player.as_table_mut()) else {
+        return;
+    };
+
+    let translation = player.get("transform")
+        .and_then(|transform| transform.get("translation"))
+        .and_then(|translation| translation.as_array())
+        .map(|values| values.iter().filter_map(|value| value.as_float()).collect::<Vec<_>>());
+    let Some(&[x, y, z]) = translation.as_deref() else {
+        return;
+    };
+
+    let position = FloatingOrigin::default().global_position(Vec3::new(x as f32, y as f32, z as f32));
+    if let Ok(position) = toml::Value::try_from(position) {
+        player.insert("position".to_string(), position);
+    }
+}
+
+pub struct SaveGamePlugin;
+
+impl Plugin for SaveGamePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveSlots>()
+            .init_resource::<SaveQueue>();
+        app.add_event::<SaveRequest>()
+            .add_event::<LoadRequest>();
+
+        app.add_systems(Update, (
+            queue_save_requests,
+            start_save_tasks,
+            poll_save_tasks,
+            handle_load_requests
+        ).chain());
+    }
+}
+
+fn queue_save_requests(mut save_event_reader: EventReader<SaveRequest>,
+                       mut save_queue: ResMut<SaveQueue>
+) {
+    for event in save_event_reader.read() {
+        if !save_queue.requested.contains(&event.slot) {
+            save_queue.requested.push(event.slot);
+        }
+    }
+}
+
+/// Take the snapshot on the main thread and write it on the [`IoTaskPool`],
+/// so the disk access never blocks a frame.
+fn start_save_tasks(mut save_queue: ResMut<SaveQueue>,
+                    save_slots: Res<SaveSlots>,
+                    origin: Res<FloatingOrigin>,
+                    chunk_manager: Res<ChunkManager>,
+                    last_checkpoint: Res<LastCheckpoint>,
+                    world_flags: Res<WorldFlags>,
+                    player_query: Query<(&Transform, &Player, &PlayerSkillAbleStats, &Inventory)>
+) {
+    if save_queue.requested.is_empty() {
+        return;
+    }
+
+    let Ok((transform, player, skills, inventory)) = player_query.get_single() else {
+        return;
+    };
+
+    if matches!(player.state, PlayerState::Dodging | PlayerState::Attacking | PlayerState::Dead) {
+        return;
+    }
+
+    let save_game = SaveGame {
+        version: SAVE_VERSION,
+        player: PlayerSave {
+            transform: *transform,
+            position: origin.global_position(transform.translation),
+            player: player.clone(),
+            skills: skills.clone(),
+            inventory: inventory.clone(),
+        },
+        world: WorldSave {
+            area: chunk_manager.current_area(origin.to_world(transform.translation)).unwrap_or_default(),
+            checkpoint_id: last_checkpoint.id.clone(),
+            checkpoint_position: last_checkpoint.position,
+            flags: world_flags.clone(),
+        },
+    };
+
+    let save_queue = &mut *save_queue;
+    let task_pool = IoTaskPool::get();
+    save_queue.requested.retain(|slot| {
+        // Wait for the running write, two writes on the same slot would race for the rename.
+        if save_queue.in_flight.iter().any(|(in_flight_slot, _)| in_flight_slot == slot) {
+            return true;
+        }
+
+        let path = save_slots.slot_path(*slot);
+        let save_game = save_game.clone();
+        let task = task_pool.spawn(async move {
+            save_game.to_toml().and_then(|content| write_save_file(&path, &content))
+        });
+        save_queue.in_flight.push((*slot, task));
+        false
+    });
+}
+
+fn poll_save_tasks(mut save_queue: ResMut<SaveQueue>) {
+    save_queue.in_flight.retain_mut(|(slot, task)| {
+        match future::block_on(future::poll_once(task)) {
+            Some(Ok(())) => {
+                info!("Saved slot {}", slot);
+                false
+            }
+            Some(Err(error)) => {
+                error!("Failed to save slot {}: {}", slot, error);
+                false
+            }
+            None => true,
+        }
+    });
+}
+
+/// Apply the save to the player and the world. A save of another area than
+/// the active one is skipped, his position means nothing in this area.
+/// Everything which is not saved, like a fall, the crouched stance, climbing
+/// or a running death sequence, is reset, so the player starts fresh at the
+/// loaded position.
+fn handle_load_requests(mut commands: Commands,
+                        mut load_event_reader: EventReader<LoadRequest>,
+                        save_slots: Res<SaveSlots>,
+                        save_queue: Res<SaveQueue>,
+                        origin: Res<FloatingOrigin>,
+                        mut chunk_manager: ResMut<ChunkManager>,
+                        mut last_checkpoint: ResMut<LastCheckpoint>,
+                        mut world_flags: ResMut<WorldFlags>,
+                        mut player_query: Query<(Entity, &mut Transform, &mut Player, &mut PlayerSkillAbleStats, &mut Inventory, BodyVelocity)>,
+                        mut transient_query: Query<(Option<&mut FallTracker>, Option<&mut Stance>, Option<&mut Climber>, Option<&DeathSequence>), With<Player>>
+) {
+    for event in load_event_reader.read() {
+        if save_queue.is_saving(event.slot) {
+            warn!("Load slot {} skipped, the slot is saved right now", event.slot);
+            continue;
+        }
+
+        let save_game = match read_save_file(&save_slots.slot_path(event.slot)) {
+            Ok(save_game) => save_game,
+            Err(error) => {
+                error!("Failed to load slot {}: {}", event.slot, error);
+                continue;
+            }
+        };
+
+        let Ok((entity, mut transform, mut player, mut skills, mut inventory, mut velocity)) = player_query.get_single_mut() else {
+            continue;
+        };
+
+        let active_area = chunk_manager.current_area(origin.to_world(transform.translation));
+        if let Some(active_area) = active_area.filter(|area| !save_game.world.area.is_empty() && *area != save_game.world.area) {
+            warn!("Load slot {} skipped, the save is in area {:?} but the active area is {:?}", event.slot, save_game.world.area, active_area);
+            continue;
+        }
+
+        // The floating origin recenters around the loaded position in the next frame.
+        *transform = save_game.player.transform
+            .with_translation(origin.local_position(&save_game.player.position));
+        *player = save_game.player.player;
+        player.state = PlayerState::default();
+        *skills = save_game.player.skills;
+        *inventory = save_game.player.inventory;
+        velocity.set_linvel(Vec3::ZERO);
+        velocity.set_angvel(Vec3::ZERO);
+
+        if let Ok((tracker, stance, climber, sequence)) = transient_query.get_mut(entity) {
+            // The drop onto the loaded position is no landing.
+            if let Some(mut tracker) = tracker {
+                *tracker = FallTracker::default();
+            }
+            if let Some(mut stance) = stance.filter(|stance| stance.crouched) {
+                *stance = Stance::default();
+                commands.entity(entity).insert(player_collider(STANDING_HALF_EXTENTS));
+            }
+            if let Some(mut climber) = climber.filter(|climber| climber.volume.is_some()) {
+                *climber = Climber::default();
+                set_body_kinematic(&mut commands, entity, false);
+            }
+            if let Some(sequence) = sequence {
+                chunk_manager.release(sequence.respawn_position);
+                commands.entity(entity).remove::<DeathSequence>();
+            }
+        }
+
+        last_checkpoint.id = save_game.world.checkpoint_id;
+        last_checkpoint.position = save_game.world.checkpoint_position;
+        *world_flags = save_game.world.flags;
+
+        info!("Loaded slot {} in area {:?}", event.slot, save_game.world.area);
+    }
+}
+
+/// Write the save atomic: the content goes to a temp file first, the old
+/// save is kept as backup and the temp file is renamed to the slot file.
+pub fn write_save_file(path: &Path, content: &str) -> Result<(), SaveError> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+
+    let temp_path = path.with_extension("toml.tmp");
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+
+    if path.exists() {
+        fs::rename(path, path.with_extension("toml.bak"))?;
+    }
+    fs::rename(&temp_path, path)?;
+
+    Ok(())
+}
+
+/// Read a save and fall back to the backup of the slot if the save is
+/// missing or corrupted.
+pub fn read_save_file(path: &Path) -> Result<SaveGame, SaveError> {
+    let result = fs::read_to_string(path)
+        .map_err(SaveError::from)
+        .and_then(|content| SaveGame::from_toml(&content));
+
+    match result {
+        Ok(save_game) => Ok(save_game),
+        Err(error) => {
+            let backup_path = path.with_extension("toml.bak");
+            warn!("Save {:?} is not readable ({}), try backup {:?}", path, error, backup_path);
+
+            let content = fs::read_to_string(&backup_path)?;
+            SaveGame::from_toml(&content)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Chunk;
+    use crate::physics::player_body;
+
+    fn test_save_game() -> SaveGame {
+        SaveGame {
+            version: SAVE_VERSION,
+            player: PlayerSave {
+                transform: Transform::from_xyz(4.0, 2.0, -8.0),
+                position: FloatingOrigin::default().global_position(Vec3::new(4.0, 2.0, -8.0)),
+                player: Player::default(),
+                skills: PlayerSkillAbleStats::default(),
+                inventory: Inventory::default(),
+            },
+            world: WorldSave {
+                area: "debug".to_string(),
+                checkpoint_id: Some("checkpoint_start".to_string()),
+                checkpoint_position: Some(Vec3::new(1.0, 0.0, 1.0)),
+                flags: WorldFlags::default(),
+            },
+        }
+    }
+
+    fn test_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("mira-game-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    /// Unit Test for check if a save survives the toml round trip.
+    #[test]
+    fn test_save_game_toml_roundtrip() {
+        let content = test_save_game().to_toml().unwrap();
+        let save_game = SaveGame::from_toml(&content).unwrap();
+
+        assert_eq!(save_game.version, SAVE_VERSION);
+        assert_eq!(save_game.player.transform.translation, Vec3::new(4.0, 2.0, -8.0));
+        assert_eq!(save_game.world.area, "debug");
+        assert_eq!(save_game.world.checkpoint_id.as_deref(), Some("checkpoint_start"));
+    }
+
+    /// Unit Test for check if a version 1 save gets his global position from the translation.
+    #[test]
+    fn test_save_game_migrate_global_position() {
+        let mut table: toml::Table = toml::from_str(&test_save_game().to_toml().unwrap()).unwrap();
+        table.insert("version".to_string(), toml::Value::Integer(1));
+        let player = table.get_mut("player").and_then(|player| player.as_table_mut()).unwrap();
+        player.remove("position");
+        player.get_mut("transform").and_then(|transform| transform.as_table_mut()).unwrap()
+            .insert("translation".to_string(), toml::Value::try_from([1300.0, 5.0, -20.0]).unwrap());
+
+        let save_game = SaveGame::from_toml(&toml::to_string(&table).unwrap()).unwrap();
+
+        assert_eq!(save_game.version, SAVE_VERSION);
+        assert_eq!(save_game.player.position.cell, bevy::math::I64Vec2::new(3, 0));
+        assert_eq!(save_game.player.position.offset, Vec3::new(-236.0, 5.0, -20.0));
+    }
+
+    /// Unit Test for check if saves of a newer game version are rejected.
+    #[test]
+    fn test_save_game_newer_version() {
+        let content = test_save_game().to_toml().unwrap()
+            .replace(&format!("version = {}", SAVE_VERSION), &format!("version = {}", SAVE_VERSION + 1));
+
+        assert!(matches!(SaveGame::from_toml(&content), Err(SaveError::UnsupportedVersion(_))));
+    }
+
+    /// Unit Test for check if loading a slot places the player and resets his
+    /// fall, stance, climbing and death sequence.
+    #[test]
+    fn test_load_resets_player() {
+        let directory = test_directory("load");
+        let save_slots = SaveSlots { directory: directory.clone() };
+        write_save_file(&save_slots.slot_path(1), &test_save_game().to_toml().unwrap()).unwrap();
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_event::<LoadRequest>();
+        app.insert_resource(save_slots)
+            .init_resource::<SaveQueue>()
+            .init_resource::<FloatingOrigin>()
+            .init_resource::<ChunkManager>()
+            .init_resource::<LastCheckpoint>()
+            .init_resource::<WorldFlags>();
+        app.add_systems(Update, handle_load_requests);
+
+        let respawn_position = Vec3::new(512.0, 0.0, 0.0);
+        app.world_mut().resource_mut::<ChunkManager>().preload(respawn_position);
+        let volume = app.world_mut().spawn_empty().id();
+        let player = app.world_mut().spawn((
+            Transform::from_xyz(100.0, 40.0, 0.0),
+            Player::default(),
+            PlayerSkillAbleStats::default(),
+            Inventory::default(),
+            FallTracker { airborne: true, peak_height: 60.0, protected: false, ..default() },
+            Stance { crouched: true, half_height: 0.3 },
+            Climber { volume: Some(volume), sliding: true },
+            DeathSequence { timer: 1.0, respawn_position },
+            player_body(STANDING_HALF_EXTENTS),
+        )).id();
+
+        app.world_mut().send_event(LoadRequest { slot: 1 });
+        app.update();
+
+        let world = app.world();
+        assert_eq!(world.get::<Transform>(player).unwrap().translation, Vec3::new(4.0, 2.0, -8.0));
+        let tracker = world.get::<FallTracker>(player).unwrap();
+        assert!(!tracker.airborne && tracker.protected);
+        assert_eq!(tracker.peak_height, 0.0);
+        assert!(!world.get::<Stance>(player).unwrap().crouched);
+        assert!(world.get::<Climber>(player).unwrap().volume.is_none());
+        assert!(world.get::<DeathSequence>(player).is_none());
+        assert!(world.resource::<ChunkManager>().preload_positions.is_empty());
+        assert_eq!(world.resource::<LastCheckpoint>().id.as_deref(), Some("checkpoint_start"));
+
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    /// Unit Test for check if a save of another area than the active one is skipped.
+    #[test]
+    fn test_load_other_area_skipped() {
+        let directory = test_directory("area");
+        let save_slots = SaveSlots { directory: directory.clone() };
+        write_save_file(&save_slots.slot_path(1), &test_save_game().to_toml().unwrap()).unwrap();
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_event::<LoadRequest>();
+        app.insert_resource(save_slots)
+            .init_resource::<SaveQueue>()
+            .init_resource::<FloatingOrigin>()
+            .init_resource::<ChunkManager>()
+            .init_resource::<LastCheckpoint>()
+            .init_resource::<WorldFlags>();
+        app.add_systems(Update, handle_load_requests);
+
+        app.world_mut().resource_mut::<ChunkManager>().chunk_entries
+            .insert((0, 0), Chunk::new((0, 0), 512, "forest", "chunk_0_0"));
+        let player = app.world_mut().spawn((
+            Transform::from_xyz(100.0, 40.0, 0.0),
+            Player::default(),
+            PlayerSkillAbleStats::default(),
+            Inventory::default(),
+            player_body(STANDING_HALF_EXTENTS),
+        )).id();
+
+        app.world_mut().send_event(LoadRequest { slot: 1 });
+        app.update();
+
+        let world = app.world();
+        assert_eq!(world.get::<Transform>(player).unwrap().translation, Vec3::new(100.0, 40.0, 0.0));
+        assert!(world.resource::<LastCheckpoint>().id.is_none());
+
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    /// Unit Test for check if a corrupted save falls back to the backup of the slot.
+    #[test]
+    fn test_corrupted_save_uses_backup() {
+        let directory = test_directory("backup");
+        let path = directory.join("slot_0.toml");
+        let content = test_save_game().to_toml().unwrap();
+
+        write_save_file(&path, &content).unwrap();
+        write_save_file(&path, "player = broken").unwrap();
+        assert!(path.with_extension("toml.bak").exists());
+        assert!(!path.with_extension("toml.tmp").exists());
+
+        let save_game = read_save_file(&path).unwrap();
+        assert_eq!(save_game.world.area, "debug");
+        assert_eq!(save_game.world.checkpoint_id.as_deref(), Some("checkpoint_start"));
+
+        let _ = fs::remove_dir_all(&directory);
+    }
+}
@@ -0,0 +1,504 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use bevy::core_pipeline::bloom::BloomSettings;
+use bevy::core_pipeline::experimental::taa::{TemporalAntiAliasBundle, TemporalAntiAliasSettings};
+use bevy::prelude::*;
+use bevy::render::settings::Backends;
+use bevy::window::{PresentMode, PrimaryWindow, WindowMode, WindowResolution};
+use bevy_kira_audio::{AudioApp, AudioChannel, AudioControl, AudioPlugin};
+use bevy_third_person_camera::{ThirdPersonCamera, Zoom};
+use serde::{Deserialize, Serialize};
+use crate::manager::{AppState, MainMenuState};
+
+/// Fog starts at this part of the fog distance and is full at the distance.
+const FOG_START_FACTOR: f32 = 0.65;
+
+//################################################# Models #################################################
+/// User settings stored as `settings.toml` in the config directory. Every
+/// field falls back to his default if it is missing or invalid, see
+/// [`GameSettings::from_toml`].
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct GameSettings {
+    pub graphics: GraphicsSettings,
+    pub controls: ControlSettings,
+    pub audio: AudioSettings,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct GraphicsSettings {
+    pub width: u32,
+    pub height: u32,
+    pub window_mode: WindowModeSetting,
+    pub vsync: bool,
+    /// Preferred render backend, CLI args and the env variable override it.
+    pub backend: RenderBackend,
+    /// Todo: apply if the upscaling pass exists, is only stored at the moment.
+    pub render_scale: f32,
+    pub taa: bool,
+    pub bloom: bool,
+    /// Distance in meters where the fog is full.
+    pub fog_distance: f32,
+    /// Vertical field of view in degrees.
+    pub fov: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ControlSettings {
+    pub camera_sensitivity: f32,
+    pub invert_y: bool,
+    /// Nearest distance of the camera to the player in meters.
+    pub min_zoom: f32,
+    /// Farthest distance of the camera to the player in meters.
+    pub max_zoom: f32,
+}
+
+/// Volumes from `0.0` up to `1.0`. The master volume scales the volume of
+/// the [`MusicChannel`] and the [`EffectsChannel`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub effects_volume: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WindowModeSetting {
+    #[default]
+    Windowed,
+    BorderlessFullscreen,
+    Fullscreen,
+}
+
+/// Render backend of the wgpu instance. [`RenderBackend::Auto`] tries every
+/// backend and [`RenderBackend::None`] runs without renderer.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderBackend {
+    #[default]
+    Auto,
+    Vulkan,
+    Metal,
+    Dx12,
+    Gl,
+    None,
+}
+
+/// Audio channel of the background music.
+#[derive(Resource, Debug, Default)]
+pub struct MusicChannel;
+
+/// Audio channel of the sound effects.
+#[derive(Resource, Debug, Default)]
+pub struct EffectsChannel;
+
+/// Location of the settings file.
+#[derive(Resource, Debug, Clone)]
+pub struct SettingsFile {
+    pub path: PathBuf,
+}
+
+//################################################# Implementations #################################################
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            width: 1270,
+            height: 720,
+            window_mode: WindowModeSetting::Windowed,
+            vsync: true,
+            backend: RenderBackend::Auto,
+            render_scale: 1.0,
+            taa: true,
+            bloom: true,
+            fog_distance: 500.0,
+            fov: 45.0,
+        }
+    }
+}
+
+impl Default for ControlSettings {
+    fn default() -> Self {
+        Self {
+            camera_sensitivity: 2.8,
+            invert_y: false,
+            min_zoom: 4.5,
+            max_zoom: 30.0,
+        }
+    }
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.0,
+            music_volume: 0.8,
+            effects_volume: 1.0,
+        }
+    }
+}
+
+impl Default for SettingsFile {
+    fn default() -> Self {
+        let path = dirs::config_dir()
+            .map(|directory| directory.join("mira-game"))
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("settings.toml");
+
+        Self { path }
+    }
+}
+
+impl From<WindowModeSetting> for WindowMode {
+    fn from(mode: WindowModeSetting) -> Self {
+        match mode {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::BorderlessFullscreen => WindowMode::BorderlessFullscreen,
+            WindowModeSetting::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+impl RenderBackend {
+    /// Parse the backend name of the CLI args or the env variable.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "auto" => Some(RenderBackend::Auto),
+            "vulkan" | "vk" => Some(RenderBackend::Vulkan),
+            "metal" => Some(RenderBackend::Metal),
+            "dx12" | "d3d12" => Some(RenderBackend::Dx12),
+            "gl" | "gles" | "opengl" | "webgl" => Some(RenderBackend::Gl),
+            "none" | "headless" => Some(RenderBackend::None),
+            _ => None,
+        }
+    }
+
+    /// The wgpu backend bits, `None` disables the renderer.
+    pub fn backends(self) -> Option<Backends> {
+        match self {
+            RenderBackend::Auto => Some(Backends::all()),
+            RenderBackend::Vulkan => Some(Backends::VULKAN),
+            RenderBackend::Metal => Some(Backends::METAL),
+            RenderBackend::Dx12 => Some(Backends::DX12),
+            RenderBackend::Gl => Some(Backends::GL),
+            RenderBackend::None => None,
+        }
+    }
+}
+
+impl GraphicsSettings {
+    fn present_mode(&self) -> PresentMode {
+        if self.vsync { PresentMode::AutoVsync } else { PresentMode::AutoNoVsync }
+    }
+
+    pub fn fog_falloff(&self) -> FogFalloff {
+        FogFalloff::Linear {
+            start: self.fog_distance * FOG_START_FACTOR,
+            end: self.fog_distance,
+        }
+    }
+}
+
+impl ControlSettings {
+    /// Camera sensitivity for the [`ThirdPersonCamera`], invert-Y flips the y axis.
+    pub fn sensitivity(&self) -> Vec2 {
+        let y = if self.invert_y { -self.camera_sensitivity } else { self.camera_sensitivity };
+        Vec2::new(self.camera_sensitivity, y)
+    }
+
+    /// Zoom bounds for the [`ThirdPersonCamera`].
+    pub fn zoom(&self) -> Zoom {
+        Zoom::new(self.min_zoom, self.max_zoom)
+    }
+}
+
+impl AudioSettings {
+    /// Volume of the [`MusicChannel`].
+    pub fn music_amplitude(&self) -> f64 {
+        (self.master_volume * self.music_volume) as f64
+    }
+
+    /// Volume of the [`EffectsChannel`].
+    pub fn effects_amplitude(&self) -> f64 {
+        (self.master_volume * self.effects_volume) as f64
+    }
+}
+
+impl GameSettings {
+    /// Read the settings file. A missing or unreadable file gives the defaults.
+    pub fn load(path: &Path) -> Self {
+        match fs::read_to_string(path) {
+            Ok(content) => Self::from_toml(&content),
+            Err(error) => {
+                info!("No settings at {:?} ({}), use defaults", path, error);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Parse the settings field by field. A field with a wrong type or a value
+    /// out of his range falls back to the default, all other fields are kept.
+    pub fn from_toml(content: &str) -> Self {
+        let loaded = match content.parse::<toml::Table>() {
+            Ok(loaded) => loaded,
+            Err(error) => {
+                warn!("Settings file is corrupted ({}), use defaults", error);
+                return Self::default();
+            }
+        };
+
+        let mut merged = toml::Table::try_from(Self::default()).expect("default settings are serializable");
+        for (section, values) in loaded.iter() {
+            let Some(values) = values.as_table() else {
+                warn!("Ignore setting {:?}, expected a section", section);
+                continue;
+            };
+
+            for (key, value) in values.iter() {
+                let mut candidate = merged.clone();
+                let Some(target) = candidate.get_mut(section).and_then(|target| target.as_table_mut()) else {
+                    warn!("Ignore unknown settings section {:?}", section);
+                    break;
+                };
+                if !target.contains_key(key) {
+                    warn!("Ignore unknown setting {}.{}", section, key);
+                    continue;
+                }
+
+                target.insert(key.clone(), value.clone());
+                match toml::Value::Table(candidate.clone()).try_into::<Self>() {
+                    Ok(_) => merged = candidate,
+                    Err(_) => warn!("Invalid setting {}.{} = {}, use default", section, key, value),
+                }
+            }
+        }
+
+        let mut settings: Self = toml::Value::Table(merged).try_into().unwrap_or_default();
+        settings.validate();
+        settings
+    }
+
+    /// Reset every value outside of his range to the default.
+    pub fn validate(&mut self) {
+        let graphics = GraphicsSettings::default();
+        let controls = ControlSettings::default();
+        let audio = AudioSettings::default();
+
+        fallback(&mut self.graphics.width, graphics.width, |width| (320..=7680).contains(width), "graphics.width");
+        fallback(&mut self.graphics.height, graphics.height, |height| (240..=4320).contains(height), "graphics.height");
+        fallback(&mut self.graphics.render_scale, graphics.render_scale, |scale| (0.25..=2.0).contains(scale), "graphics.render_scale");
+        fallback(&mut self.graphics.fog_distance, graphics.fog_distance, |distance| (50.0..=5000.0).contains(distance), "graphics.fog_distance");
+        fallback(&mut self.graphics.fov, graphics.fov, |fov| (30.0..=120.0).contains(fov), "graphics.fov");
+        fallback(&mut self.controls.camera_sensitivity, controls.camera_sensitivity, |sensitivity| (0.1..=20.0).contains(sensitivity), "controls.camera_sensitivity");
+        fallback(&mut self.controls.min_zoom, controls.min_zoom, |zoom| (1.0..=50.0).contains(zoom), "controls.min_zoom");
+        let min_zoom = self.controls.min_zoom;
+        fallback(&mut self.controls.max_zoom, controls.max_zoom.max(min_zoom), |zoom| (min_zoom..=100.0).contains(zoom), "controls.max_zoom");
+        fallback(&mut self.audio.master_volume, audio.master_volume, |volume| (0.0..=1.0).contains(volume), "audio.master_volume");
+        fallback(&mut self.audio.music_volume, audio.music_volume, |volume| (0.0..=1.0).contains(volume), "audio.music_volume");
+        fallback(&mut self.audio.effects_volume, audio.effects_volume, |volume| (0.0..=1.0).contains(volume), "audio.effects_volume");
+    }
+
+    /// Primary window created from the graphics settings.
+    pub fn window(&self, title: &str) -> Window {
+        Window {
+            title: title.to_string(),
+            resolution: WindowResolution::new(self.graphics.width as f32, self.graphics.height as f32),
+            mode: self.graphics.window_mode.into(),
+            present_mode: self.graphics.present_mode(),
+            ..default()
+        }
+    }
+}
+
+fn fallback<T: PartialEq + std::fmt::Debug>(value: &mut T, default: T, valid: impl Fn(&T) -> bool, name: &str) {
+    if !valid(value) {
+        warn!("Setting {} = {:?} is out of range, use {:?}", name, value, default);
+        *value = default;
+    }
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SettingsFile>()
+            .init_resource::<GameSettings>();
+
+        app.add_systems(Update, (
+            apply_window_settings,
+            apply_camera_settings
+        ).run_if(resource_changed::<GameSettings>));
+
+        app.add_systems(OnExit(AppState::MainMenu(MainMenuState::Settings)), save_settings);
+
+        // Only the windowed game has audio, the headless run has no output device.
+        if app.is_plugin_added::<AudioPlugin>() {
+            app.add_audio_channel::<MusicChannel>()
+                .add_audio_channel::<EffectsChannel>();
+            app.add_systems(Update, apply_audio_settings.run_if(resource_changed::<GameSettings>));
+        }
+    }
+}
+
+fn apply_window_settings(settings: Res<GameSettings>,
+                         mut window_query: Query<&mut Window, With<PrimaryWindow>>
+) {
+    for mut window in window_query.iter_mut() {
+        window.resolution.set(settings.graphics.width as f32, settings.graphics.height as f32);
+        window.mode = settings.graphics.window_mode.into();
+        window.present_mode = settings.graphics.present_mode();
+    }
+}
+
+fn apply_camera_settings(mut commands: Commands,
+                         settings: Res<GameSettings>,
+                         mut camera_query: Query<(Entity, &mut ThirdPersonCamera, &mut Projection, &mut FogSettings, Has<BloomSettings>, Has<TemporalAntiAliasSettings>)>
+) {
+    for (entity, mut camera, mut projection, mut fog, has_bloom, has_taa) in camera_query.iter_mut() {
+        camera.sensitivity = settings.controls.sensitivity();
+        // Only the bounds change, the camera keeps his current distance.
+        camera.zoom.min = settings.controls.min_zoom;
+        camera.zoom.max = settings.controls.max_zoom;
+        fog.falloff = settings.graphics.fog_falloff();
+        if let Projection::Perspective(perspective) = projection.as_mut() {
+            perspective.fov = settings.graphics.fov.to_radians();
+        }
+
+        match (settings.graphics.bloom, has_bloom) {
+            (true, false) => { commands.entity(entity).insert(BloomSettings::default()); }
+            (false, true) => { commands.entity(entity).remove::<BloomSettings>(); }
+            _ => {}
+        }
+
+        match (settings.graphics.taa, has_taa) {
+            (true, false) => { commands.entity(entity).insert(TemporalAntiAliasBundle::default()); }
+            (false, true) => { commands.entity(entity).remove::<TemporalAntiAliasBundle>(); }
+            _ => {}
+        }
+    }
+}
+
+fn apply_audio_settings(settings: Res<GameSettings>,
+                        music_channel: Res<AudioChannel<MusicChannel>>,
+                        effects_channel: Res<AudioChannel<EffectsChannel>>
+) {
+    music_channel.set_volume(settings.audio.music_amplitude());
+    effects_channel.set_volume(settings.audio.effects_amplitude());
+}
+
+fn save_settings(settings: Res<GameSettings>, settings_file: Res<SettingsFile>) {
+    match settings.save(&settings_file.path) {
+        Ok(()) => info!("Saved settings to {:?}", settings_file.path),
+        Err(error) => error!("Failed to save settings to {:?}: {}", settings_file.path, error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if the settings survive a write and read.
+    #[test]
+    fn test_settings_toml_roundtrip() {
+        let mut settings = GameSettings::default();
+        settings.graphics.window_mode = WindowModeSetting::BorderlessFullscreen;
+        settings.controls.invert_y = true;
+        settings.audio.music_volume = 0.3;
+
+        let content = toml::to_string_pretty(&settings).unwrap();
+        assert_eq!(GameSettings::from_toml(&content), settings);
+    }
+
+    /// Unit Test for check if missing, wrong typed and out of range values fall back per field.
+    #[test]
+    fn test_settings_fallback_per_field() {
+        let content = r#"
+            [graphics]
+            width = 1920
+            height = "tall"
+            fov = 500.0
+            window_mode = "Floating"
+            vsync = false
+            backend = "Glide"
+
+            render_scale = 0.1
+
+            [controls]
+            camera_sensitivity = 4
+            min_zoom = 12.0
+            max_zoom = 8.0
+        "#;
+
+        let settings = GameSettings::from_toml(content);
+        let defaults = GameSettings::default();
+        assert_eq!(settings.graphics.width, 1920);
+        assert_eq!(settings.graphics.height, defaults.graphics.height);
+        assert_eq!(settings.graphics.fov, defaults.graphics.fov);
+        assert_eq!(settings.graphics.window_mode, defaults.graphics.window_mode);
+        assert!(!settings.graphics.vsync);
+        assert_eq!(settings.graphics.backend, RenderBackend::Auto);
+        assert_eq!(settings.graphics.render_scale, defaults.graphics.render_scale);
+        assert_eq!(settings.controls.camera_sensitivity, 4.0);
+        assert_eq!(settings.controls.min_zoom, 12.0);
+        assert_eq!(settings.controls.max_zoom, defaults.controls.max_zoom);
+        assert_eq!(settings.audio, defaults.audio);
+    }
+
+    /// Unit Test for check if invert-Y flips only the vertical sensitivity.
+    #[test]
+    fn test_invert_y_sensitivity() {
+        let controls = ControlSettings {
+            camera_sensitivity: 2.0,
+            invert_y: true,
+            ..default()
+        };
+
+        assert_eq!(controls.sensitivity(), Vec2::new(2.0, -2.0));
+    }
+
+    /// Unit Test for check if changed zoom settings update the zoom bounds of the camera.
+    #[test]
+    fn test_apply_camera_zoom() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        let mut settings = GameSettings::default();
+        settings.graphics.bloom = false;
+        settings.graphics.taa = false;
+        settings.controls.min_zoom = 2.0;
+        settings.controls.max_zoom = 12.0;
+        app.insert_resource(settings);
+        app.add_systems(Update, apply_camera_settings);
+
+        let camera = app.world_mut().spawn((
+            ThirdPersonCamera::default(),
+            Projection::default(),
+            FogSettings::default(),
+        )).id();
+        app.update();
+
+        let zoom = &app.world().get::<ThirdPersonCamera>(camera).unwrap().zoom;
+        assert_eq!((zoom.min, zoom.max), (2.0, 12.0));
+    }
+
+    /// Unit Test for check if the master volume scales the channel volumes.
+    #[test]
+    fn test_channel_volumes_with_master_volume() {
+        let audio = AudioSettings {
+            master_volume: 0.5,
+            music_volume: 0.8,
+            effects_volume: 1.0,
+        };
+
+        assert!((audio.music_amplitude() - 0.4).abs() < 1e-6);
+        assert_eq!(audio.effects_amplitude(), 0.5);
+    }
+}
@@ -1,65 +1,249 @@
+mod cli;
 mod manager;
 mod entities;
+mod headless;
 mod environment;
 mod logic;
+mod physics;
 
+use bevy::animation::AnimationPlugin;
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::core_pipeline::CorePipelinePlugin;
+use bevy::gizmos::GizmoPlugin;
+use bevy::gltf::GltfPlugin;
+use bevy::input::InputPlugin;
+use bevy::log::LogPlugin;
+use bevy::pbr::PbrPlugin;
 use bevy::prelude::*;
-use bevy::window::{WindowPlugin, Window, WindowResolution};
-use bevy::render::settings::{WgpuSettings, Backends, WgpuFeatures, RenderCreation};
+use bevy::render::texture::ImagePlugin;
+use bevy::scene::ScenePlugin;
+use bevy::state::app::StatesPlugin;
+use bevy::window::{ExitCondition, WindowPlugin};
+use bevy::render::settings::{WgpuSettings, WgpuSettingsPriority, Backends, RenderCreation};
 use bevy::app::AppExit;
 use bevy::render::RenderPlugin;
-use crate::manager::ManagerPlugin;
+use bevy_kira_audio::AudioPlugin;
+use crate::logic::{GameSettings, InputRecording, RenderBackend, SettingsFile};
+use clap::Parser;
+use crate::cli::{Cli, CliPlugin};
+use crate::headless::{HeadlessConfig, HeadlessPlugin};
+use crate::manager::{ManagerPlugin, RunMode};
 
-/// Main function
+/// Main function. Invalid arguments print the usage error and exit with
+/// a non-zero [`AppExit`].
 fn main() -> AppExit {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(error) => {
+            let _ = error.print();
+            return AppExit::from_code(error.exit_code() as u8);
+        }
+    };
+
+    let replay = match cli.replay.as_ref().map(|path| InputRecording::load(path)).transpose() {
+        Ok(replay) => replay,
+        Err(error) => {
+            eprintln!("error: failed to read the input recording: {}", error);
+            return AppExit::error();
+        }
+    };
+
     let mut app = App::new();
-    initialize_app(&mut app).run()
+    initialize_app(&mut app, CliPlugin { cli, replay }).run()
 }
 
 /// Function initialized the main game loop and set up [`manager`].
 /// All the game logic can be found at [`manager`] or his sub
 /// packages like [`entities`], [`logic`] or [`environment`].
-fn initialize_app(app: &mut App) -> &mut App {
+/// The [`GameSettings`] are read before the plugins because the window is
+/// created from them.
+fn initialize_app(app: &mut App, cli_plugin: CliPlugin) -> &mut App {
+    let settings_file = SettingsFile::default();
+    let mut settings = GameSettings::load(&settings_file.path);
+    cli_plugin.cli.apply_settings(&mut settings);
+
+    let log_plugin = LogPlugin {
+        level: cli_plugin.cli.log_level,
+        filter: cli_plugin.cli.log_filter.clone(),
+        ..default()
+    };
+
+    if let Some(config) = cli_plugin.cli.headless_config() {
+        return initialize_headless_app(app, config, log_plugin)
+            .insert_resource(settings)
+            .insert_resource(settings_file)
+            .add_plugins((cli_plugin, ManagerPlugin));
+    }
+
+    let mut backend_warnings = Vec::new();
+    let backend = select_render_backend(&settings, cli_plugin.cli.backend, std::env::var(RENDER_BACKEND_ENV).ok(), &mut backend_warnings);
+    let gpu_settings = create_gpu_settings(backend, &mut backend_warnings);
+
     app
         .add_plugins(DefaultPlugins.set(
             WindowPlugin {
-                primary_window: Some(Window {
-                    title: "Mira | Development State 0.1.0-alpha".to_string(),
-                    resolution: WindowResolution::new(1270.0, 720.0),
-                    ..default()
-                }),
+                primary_window: Some(settings.window("Mira | Development State 0.1.0-alpha")),
                 ..default()
             }
         ).set(
             RenderPlugin {
-                render_creation: RenderCreation::Automatic(create_gpu_settings()),
+                render_creation: RenderCreation::Automatic(gpu_settings),
                 ..default()
             }
-        )).add_plugins(ManagerPlugin)
+        ).set(log_plugin))
+        .add_plugins(AudioPlugin)
+        .insert_resource(settings)
+        .insert_resource(settings_file)
+        .insert_resource(BackendWarnings(backend_warnings))
+        .add_systems(Startup, log_backend_warnings)
+        .add_plugins((cli_plugin, ManagerPlugin))
+}
+
+/// Headless variant of [`initialize_app`] for CI and as dedicated server. It
+/// uses [`MinimalPlugins`] with assets, scenes and the gameplay, but has no
+/// window and no GPU. The renderer plugins are still added for the mesh and
+/// material assets of the glb files, they do nothing without backend.
+fn initialize_headless_app(app: &mut App, config: HeadlessConfig, log_plugin: LogPlugin) -> &mut App {
+    app
+        .add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(config.tick())))
+        .add_plugins((
+            log_plugin,
+            TransformPlugin,
+            HierarchyPlugin,
+            InputPlugin,
+            StatesPlugin,
+            WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            },
+            AssetPlugin::default(),
+            ScenePlugin,
+        ))
+        .add_plugins((
+            RenderPlugin {
+                render_creation: RenderCreation::Automatic(create_gpu_settings(RenderBackend::None, &mut Vec::new())),
+                ..default()
+            },
+            ImagePlugin::default(),
+            CorePipelinePlugin,
+            PbrPlugin::default(),
+            GltfPlugin::default(),
+            AnimationPlugin,
+            GizmoPlugin,
+        ))
+        .add_plugins(HeadlessPlugin { config })
+        .insert_resource(RunMode::Headless)
+}
+
+/// Name of the env variable which selects the render backend.
+const RENDER_BACKEND_ENV: &str = "MIRA_RENDER_BACKEND";
+
+/// Backends in the order they are tried if the selected one has no adapter.
+const FALLBACK_BACKENDS: [Backends; 4] = [Backends::VULKAN, Backends::METAL, Backends::DX12, Backends::GL];
+
+/// Warnings of the render backend selection. The backend is selected before
+/// the [`LogPlugin`] is set up, so they are logged at startup.
+#[derive(Resource, Debug, Default)]
+struct BackendWarnings(Vec<String>);
+
+fn log_backend_warnings(warnings: Res<BackendWarnings>) {
+    for warning in warnings.0.iter() {
+        warn!("{}", warning);
+    }
+}
+
+/// Select the render backend. The `--backend` argument wins over the
+/// [`RENDER_BACKEND_ENV`] variable and this one over the [`GameSettings`].
+/// An unknown backend name is pushed to `warnings`.
+fn select_render_backend(settings: &GameSettings,
+                         cli_backend: Option<RenderBackend>,
+                         env: Option<String>,
+                         warnings: &mut Vec<String>
+) -> RenderBackend {
+    if let Some(backend) = cli_backend {
+        return backend;
+    }
+
+    if let Some(name) = env {
+        match RenderBackend::parse(&name) {
+            Some(backend) => return backend,
+            None => warnings.push(format!("Unknown render backend {:?} in {}, ignore it", name, RENDER_BACKEND_ENV)),
+        }
+    }
+
+    settings.graphics.backend
 }
 
 /// Function was out sourced for Unit testing and inclusion for
-/// [`WgpuSettings`].
-fn create_gpu_settings() -> WgpuSettings {
+/// [`WgpuSettings`]. Optional features like `POLYGON_MODE_LINE` are not
+/// required, [`WgpuSettingsPriority::Functionality`] enables everything the
+/// adapter supports.
+fn create_gpu_settings(backend: RenderBackend, warnings: &mut Vec<String>) -> WgpuSettings {
     WgpuSettings {
-        features: WgpuFeatures::POLYGON_MODE_LINE,
-        backends: Some(Backends::VULKAN),
+        backends: backend.backends().map(|backends| resolve_backends(backends, warnings)),
+        priority: WgpuSettingsPriority::Functionality,
         ..default()
     }
 }
 
+/// Use the preferred backends if there is an adapter for them, otherwise the
+/// first of [`FALLBACK_BACKENDS`] with an adapter. Every backend without
+/// adapter is pushed to `warnings`.
+fn resolve_backends(preferred: Backends, warnings: &mut Vec<String>) -> Backends {
+    if cfg!(target_arch = "wasm32") {
+        return Backends::GL;
+    }
+
+    std::iter::once(preferred)
+        .chain(FALLBACK_BACKENDS)
+        .find(|backends| {
+            let found = has_adapter(*backends);
+            if !found {
+                warnings.push(format!("No render adapter for {:?}, try the next backend", backends));
+            }
+            found
+        })
+        .unwrap_or(preferred)
+}
+
+fn has_adapter(backends: Backends) -> bool {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends,
+        ..default()
+    });
+
+    !instance.enumerate_adapters(backends).is_empty()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use bevy::render::settings::WgpuFeatures;
 
-    /// Unit Test for check if [`Backends::VULKAN`] enabled.
+    /// Unit Test for check if no optional feature is forced at the renderer.
     #[test]
-    fn test_app_uses_vulkan_backend() {
-        let settings = create_gpu_settings();
+    fn test_gpu_settings_request_no_optional_features() {
+        let settings = create_gpu_settings(RenderBackend::None, &mut Vec::new());
 
-        assert_eq!(settings.backends, Some(Backends::VULKAN));
-        assert!(settings.features.contains(WgpuFeatures::POLYGON_MODE_LINE));
+        assert_eq!(settings.backends, None);
+        assert!(!settings.features.contains(WgpuFeatures::POLYGON_MODE_LINE));
+        assert!(matches!(settings.priority, WgpuSettingsPriority::Functionality));
     }
-}
 
+    /// Unit Test for check if CLI args win over the env variable and the env variable over
+    /// the settings. An unknown env backend is returned as warning.
+    #[test]
+    fn test_select_render_backend_priority() {
+        let mut settings = GameSettings::default();
+        settings.graphics.backend = RenderBackend::Dx12;
+        let mut warnings = Vec::new();
 
+        assert_eq!(select_render_backend(&settings, Some(RenderBackend::Gl), Some("vulkan".to_string()), &mut warnings), RenderBackend::Gl);
+        assert_eq!(select_render_backend(&settings, None, Some("vulkan".to_string()), &mut warnings), RenderBackend::Vulkan);
+        assert!(warnings.is_empty());
+        assert_eq!(select_render_backend(&settings, None, Some("glide".to_string()), &mut warnings), RenderBackend::Dx12);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(select_render_backend(&settings, None, None, &mut warnings), RenderBackend::Dx12);
+    }
+}
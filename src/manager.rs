@@ -1,12 +1,13 @@
 use bevy::input::common_conditions::input_toggle_active;
+use bevy::pbr::wireframe::{WireframeConfig, WireframePlugin};
 use bevy::prelude::*;
+use bevy::render::renderer::RenderDevice;
+use bevy::render::settings::WgpuFeatures;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
-use bevy_rapier3d::plugin::{NoUserData, RapierPhysicsPlugin};
-use bevy_rapier3d::prelude::{DebugRenderStyle, RapierDebugRenderPlugin};
-use bevy_xpbd_3d::plugins::PhysicsPlugins;
 use crate::entities::EntitiesPlugin;
 use crate::environment::EnvironmentPlugin;
 use crate::logic::LogicPlugin;
+use crate::physics::PhysicsBackendPlugin;
 
 /// Enum for the states. states have his own internal states for handle in
 /// bound states like [`MainMenu`] handle [`MainMenuState`] if there was called.
@@ -21,10 +22,11 @@ pub enum AppState {
     Quit
 }
 
-/// Load the default initialize value for [`AppState`].
+/// Load the default initialize value for [`AppState`]. The game starts at
+/// the loading screen, it switches to [`AppState::InGame`] if everything is loaded.
 impl Default for AppState {
     fn default() -> Self {
-        AppState::InGame(InGameState::default())
+        AppState::WaitingScreen
     }
 }
 
@@ -61,6 +63,29 @@ impl Default for InGameState {
     }
 }
 
+/// How the game binary runs. [`RunMode::Headless`] has no window and no
+/// GPU, so every plugin which needs them is skipped. Insert the resource
+/// before [`ManagerPlugin`] is added.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RunMode {
+    #[default]
+    Windowed,
+    Headless,
+}
+
+impl RunMode {
+    /// Run mode of the app, [`RunMode::Windowed`] if no resource was inserted.
+    pub fn of(app: &App) -> Self {
+        app.world().get_resource::<RunMode>().copied().unwrap_or_default()
+    }
+}
+
+/// Debug tools which are enabled at the start, set by the CLI.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct DebugOptions {
+    pub inspector: bool,
+}
+
 /// [`SystemSet`] for handle audio systems and put them to a set list.
 #[derive(SystemSet, Debug, Clone, Hash, PartialEq, Eq)]
 pub struct AudioSets;
@@ -95,12 +120,18 @@ pub struct ManagerPlugin;
 impl Plugin for ManagerPlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<AppState>();
+        // The player only runs in game, behind the loading screen his chunks are not streamed in yet.
+        app.configure_sets(Update, PlayerSets.run_if(in_game));
 
-        app.add_plugins(WorldInspectorPlugin::default().run_if(input_toggle_active(false, KeyCode::F1)));
+        if RunMode::of(app) == RunMode::Windowed {
+            let inspector = app.world().get_resource::<DebugOptions>().map_or(false, |options| options.inspector);
+            app.add_plugins(WorldInspectorPlugin::default().run_if(input_toggle_active(inspector, KeyCode::F1)));
 
-        app.add_plugins(PhysicsPlugins::default())
-            .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
-            .add_plugins(plugin_init_rapier3d_debug());
+            app.add_plugins(WireframePlugin);
+            app.add_systems(Update, toggle_wireframe);
+        }
+
+        app.add_plugins(PhysicsBackendPlugin);
 
         app.add_plugins((
             EnvironmentPlugin,
@@ -110,17 +141,29 @@ impl Plugin for ManagerPlugin {
     }
 }
 
-fn plugin_init_rapier3d_debug() -> RapierDebugRenderPlugin {
-    RapierDebugRenderPlugin {
-        enabled: true,
-        style: DebugRenderStyle {
-            collider_parentless_color: [0.0, 1.0, 1.0, 1.0],
-            collider_dynamic_color: [305.0, 1.0, 0.5, 1.0],
-            collider_fixed_color: [65.0, 1.0, 0.5, 1.0],
-            collider_kinematic_color: [140.0, 1.0, 0.5, 1.0],
-            sleep_color_multiplier: [0.0, 0.5, 0.5, 1.0],
-            ..default()
-        },
-        ..default()
+/// Run condition which is true in every [`AppState::InGame`] state.
+pub fn in_game(state: Res<State<AppState>>) -> bool {
+    matches!(state.get(), AppState::InGame(_))
+}
+
+/// Toggle the global wireframe with F2. The wireframe needs the
+/// `POLYGON_MODE_LINE` feature, without it the toggle stays off.
+fn toggle_wireframe(keyboard: Res<ButtonInput<KeyCode>>,
+                    render_device: Option<Res<RenderDevice>>,
+                    mut wireframe_config: ResMut<WireframeConfig>
+) {
+    let wireframe_key = KeyCode::F2;
+    if !keyboard.just_pressed(wireframe_key) {
+        return;
     }
-}
\ No newline at end of file
+
+    let supported = render_device
+        .map_or(false, |device| device.features().contains(WgpuFeatures::POLYGON_MODE_LINE));
+    if !supported {
+        warn!("Wireframe is not supported by the render adapter");
+        wireframe_config.global = false;
+        return;
+    }
+
+    wireframe_config.global = !wireframe_config.global;
+}
@@ -0,0 +1,249 @@
+#[cfg(feature = "physics-rapier")]
+mod rapier;
+#[cfg(feature = "physics-xpbd")]
+mod xpbd;
+
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+
+/// Distance two grid values may differ and still count as the same, relative
+/// to the mesh size. Covers the rounding of exported meshes.
+const GRID_TOLERANCE: f32 = 1e-4;
+
+#[cfg(all(feature = "physics-rapier", feature = "physics-xpbd"))]
+compile_error!("The features `physics-rapier` and `physics-xpbd` are exclusive. Build with `--no-default-features --features physics-xpbd` for XPBD.");
+
+#[cfg(not(any(feature = "physics-rapier", feature = "physics-xpbd")))]
+compile_error!("No physics backend selected. Enable the feature `physics-rapier` or `physics-xpbd`.");
+
+/// The active physics backend. Every backend exposes the same set of
+/// functions and types so the game code never touches the engine crates directly:
+/// [`PhysicsBackendPlugin`], [`BodyVelocity`], [`PhysicsQuery`], [`player_body`], [`player_collider`],
+/// [`terrain_body`], [`baked_terrain_body`], [`heightfield_body`], [`remove_terrain_body`], [`set_body_kinematic`] and [`set_collider_enabled`].
+#[cfg(feature = "physics-rapier")]
+pub use crate::physics::rapier::*;
+#[cfg(feature = "physics-xpbd")]
+pub use crate::physics::xpbd::*;
+
+//################################################# Models #################################################
+/// Backend independent result of a shape cast made with [`PhysicsQuery`].
+/// The normal points away from the hit surface in world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapeHit {
+    pub entity: Entity,
+    pub distance: f32,
+    pub normal: Vec3,
+}
+
+/// Backend independent trimesh of a terrain collider. It is baked once from
+/// a mesh and turned into a body with [`baked_terrain_body`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainCollider {
+    pub vertices: Vec<Vec3>,
+    pub indices: Vec<[u32; 3]>,
+}
+
+/// Backend independent heightfield of a terrain collider, turned into a body
+/// with [`heightfield_body`]. The samples form a grid of `rows` along z and
+/// `columns` along x, which spans `size` and is centered on the body. The
+/// `heights` are stored row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainHeightfield {
+    pub heights: Vec<f32>,
+    pub rows: usize,
+    pub columns: usize,
+    pub size: Vec2,
+}
+
+/// Baked collider of a terrain mesh. Meshes on a regular grid become a
+/// heightfield, which needs less memory and is faster to ray cast.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TerrainShape {
+    Trimesh(TerrainCollider),
+    Heightfield(TerrainHeightfield),
+}
+
+//################################################# Implementations #################################################
+impl TerrainCollider {
+    /// Bake the trimesh of `mesh`. Vertices with the same position are welded
+    /// and triangles without area are removed. Returns [`None`] if the mesh
+    /// is no triangle list or has no triangle left.
+    pub fn from_mesh(mesh: &Mesh) -> Option<Self> {
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            return None;
+        }
+
+        let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION)?.as_float3()?;
+        let indices = match mesh.indices() {
+            Some(indices) => indices.iter().collect::<Vec<_>>(),
+            None => (0..positions.len()).collect(),
+        };
+
+        let mut collider = TerrainCollider { vertices: Vec::new(), indices: Vec::new() };
+        let mut lookup = std::collections::HashMap::new();
+        for triangle in indices.chunks_exact(3) {
+            let mut welded = [0; 3];
+            for (target, index) in welded.iter_mut().zip(triangle) {
+                let position = *positions.get(*index)?;
+                *target = *lookup.entry(position.map(f32::to_bits)).or_insert_with(|| {
+                    collider.vertices.push(Vec3::from(position));
+                    collider.vertices.len() as u32 - 1
+                });
+            }
+
+            let [a, b, c] = welded.map(|index| collider.vertices[index as usize]);
+            if (b - a).cross(c - a).length_squared() > f32::EPSILON {
+                collider.indices.push(welded);
+            }
+        }
+
+        (!collider.indices.is_empty()).then_some(collider)
+    }
+}
+
+impl TerrainHeightfield {
+    /// Read the heightfield of `mesh` if his vertices form a regular grid on
+    /// the xz plane centered on the origin, with one height per grid point and
+    /// two triangles per cell. Returns [`None`] for any other geometry, like
+    /// overhangs, holes or an irregular grid.
+    pub fn from_mesh(mesh: &Mesh) -> Option<Self> {
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            return None;
+        }
+
+        let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION)?.as_float3()?;
+        let (min, max) = positions.iter()
+            .map(|position| Vec3::from(*position).xz())
+            .fold((Vec2::MAX, Vec2::MIN), |(min, max), position| (min.min(position), max.max(position)));
+        let size = max - min;
+        let tolerance = size.max_element() * GRID_TOLERANCE;
+        if size.min_element() <= 0.0 || (min + max).abs().max_element() / 2.0 > tolerance {
+            return None;
+        }
+
+        let columns = grid_lines(positions.iter().map(|position| position[0]), tolerance)?;
+        let rows = grid_lines(positions.iter().map(|position| position[2]), tolerance)?;
+        let step = size / Vec2::new(columns as f32 - 1.0, rows as f32 - 1.0);
+
+        let mut heights: Vec<Option<f32>> = vec![None; rows * columns];
+        for position in positions {
+            let cell = (Vec2::new(position[0], position[2]) - min) / step;
+            let point = cell.round();
+            if ((cell - point) * step).abs().max_element() > tolerance {
+                return None;
+            }
+
+            // A second height at the same point is an overhang or a wall.
+            let height = &mut heights[point.y as usize * columns + point.x as usize];
+            match height {
+                Some(height) if (*height - position[1]).abs() > tolerance => return None,
+                _ => *height = Some(position[1]),
+            }
+        }
+
+        let triangles = mesh.indices().map_or(positions.len(), |indices| indices.len()) / 3;
+        if triangles != (rows - 1) * (columns - 1) * 2 {
+            return None;
+        }
+
+        Some(Self {
+            heights: heights.into_iter().collect::<Option<Vec<_>>>()?,
+            rows,
+            columns,
+            size,
+        })
+    }
+}
+
+impl TerrainShape {
+    /// Heightfield of `mesh` if it is a regular grid, otherwise the trimesh.
+    pub fn from_mesh(mesh: &Mesh) -> Option<Self> {
+        match TerrainHeightfield::from_mesh(mesh) {
+            Some(heightfield) => Some(TerrainShape::Heightfield(heightfield)),
+            None => TerrainCollider::from_mesh(mesh).map(TerrainShape::Trimesh),
+        }
+    }
+}
+
+/// Count of the evenly spaced grid lines the `values` lie on, [`None`] if
+/// there are less than two or the spacing differs.
+fn grid_lines(values: impl Iterator<Item = f32>, tolerance: f32) -> Option<usize> {
+    let mut lines = values.collect::<Vec<_>>();
+    lines.sort_by(f32::total_cmp);
+    lines.dedup_by(|line, previous| (*line - *previous).abs() <= tolerance);
+    if lines.len() < 2 {
+        return None;
+    }
+
+    let step = (lines[lines.len() - 1] - lines[0]) / (lines.len() - 1) as f32;
+    lines.windows(2)
+        .all(|pair| (pair[1] - pair[0] - step).abs() <= tolerance)
+        .then_some(lines.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if the [`player_body`] bundle can be moved through
+    /// [`BodyVelocity`] without knowing the active backend.
+    #[test]
+    fn test_player_body_velocity_roundtrip() {
+        let mut world = World::new();
+        let entity = world.spawn(player_body(Vec3::splat(0.5))).id();
+
+        let mut query = world.query::<BodyVelocity>();
+        let mut velocity = query.get_mut(&mut world, entity).unwrap();
+        velocity.set_linvel(Vec3::new(1.0, 2.0, 3.0));
+        velocity.set_angvel(Vec3::ZERO);
+
+        let velocity = query.get(&world, entity).unwrap();
+        assert_eq!(velocity.linvel(), Vec3::new(1.0, 2.0, 3.0));
+    }
+
+    /// Unit Test for check that a missing mesh attribute gives no terrain collider
+    /// instead of a panic.
+    #[test]
+    fn test_terrain_body_without_positions() {
+        let mesh = Mesh::new(
+            bevy::render::mesh::PrimitiveTopology::TriangleList,
+            bevy::render::render_asset::RenderAssetUsages::default(),
+        );
+
+        assert!(terrain_body(&mesh).is_none());
+        assert!(TerrainCollider::from_mesh(&mesh).is_none());
+    }
+
+    /// Unit Test for check if the baked terrain collider welds the shared vertices of a plane.
+    #[test]
+    fn test_terrain_collider_from_mesh() {
+        let mesh = Plane3d::default().mesh().size(2.0, 2.0).build();
+        let collider = TerrainCollider::from_mesh(&mesh).unwrap();
+
+        assert_eq!(collider.vertices.len(), 4);
+        assert_eq!(collider.indices.len(), 2);
+    }
+
+    /// Unit Test for check if a grid mesh becomes a heightfield and other
+    /// geometry falls back to the trimesh.
+    #[test]
+    fn test_terrain_shape_from_mesh() {
+        let mut mesh = Plane3d::default().mesh().size(8.0, 4.0).subdivisions(3).build();
+        if let Some(bevy::render::mesh::VertexAttributeValues::Float32x3(positions)) = mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION) {
+            positions.iter_mut().for_each(|position| position[1] = position[0] * 0.5);
+        }
+
+        let Some(TerrainShape::Heightfield(heightfield)) = TerrainShape::from_mesh(&mesh) else {
+            panic!("grid mesh is no heightfield");
+        };
+        assert_eq!((heightfield.rows, heightfield.columns), (5, 5));
+        assert_eq!(heightfield.size, Vec2::new(8.0, 4.0));
+        assert_eq!(&heightfield.heights[0..5], &[-2.0, -1.0, 0.0, 1.0, 2.0]);
+
+        let cuboid = Cuboid::from_size(Vec3::splat(2.0)).mesh().build();
+        assert!(matches!(TerrainShape::from_mesh(&cuboid), Some(TerrainShape::Trimesh(_))));
+
+        let shifted = Plane3d::default().mesh().size(4.0, 4.0).build().translated_by(Vec3::X);
+        assert!(TerrainHeightfield::from_mesh(&shifted).is_none());
+    }
+}
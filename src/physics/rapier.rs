@@ -0,0 +1,259 @@
+use bevy::ecs::query::QueryData;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use crate::manager::{in_game, AppState};
+use crate::physics::{ShapeHit, TerrainCollider, TerrainHeightfield};
+
+/// Name of the backend, used for logs and the benchmark output.
+pub const BACKEND_NAME: &str = "rapier";
+
+/// Plugin which adds the rapier simulation and his debug renderer.
+pub struct PhysicsBackendPlugin;
+
+impl Plugin for PhysicsBackendPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
+            .add_plugins(plugin_init_rapier3d_debug());
+
+        // Bodies would fall through the chunks which are not streamed in yet.
+        app.add_systems(Update, update_simulation_active.run_if(state_changed::<AppState>));
+
+        info!("Physics backend: {}", BACKEND_NAME);
+    }
+}
+
+/// Run the simulation only in game, it is paused behind the loading screen.
+fn update_simulation_active(state: Res<State<AppState>>,
+                            mut config: ResMut<RapierConfiguration>
+) {
+    config.physics_pipeline_active = in_game(state);
+}
+
+/// Velocity access of a rigid body. Use it inside a [`Query`] like
+/// `Query<BodyVelocity>` and change the values over the item functions.
+#[derive(QueryData)]
+#[query_data(mutable)]
+pub struct BodyVelocity {
+    velocity: &'static mut Velocity,
+}
+
+impl BodyVelocityItem<'_> {
+    pub fn linvel(&self) -> Vec3 {
+        self.velocity.linvel
+    }
+
+    pub fn set_linvel(&mut self, linvel: Vec3) {
+        self.velocity.linvel = linvel;
+    }
+
+    pub fn set_angvel(&mut self, angvel: Vec3) {
+        self.velocity.angvel = angvel;
+    }
+}
+
+impl BodyVelocityReadOnlyItem<'_> {
+    pub fn linvel(&self) -> Vec3 {
+        self.velocity.linvel
+    }
+}
+
+/// [`SystemParam`] for scene queries like shape casts.
+#[derive(SystemParam)]
+pub struct PhysicsQuery<'w> {
+    context: Res<'w, RapierContext>,
+}
+
+impl PhysicsQuery<'_> {
+    /// Cast a sphere straight down and ignore the collider of `exclude`.
+    /// Hits fixed, kinematic and dynamic bodies but no sensors.
+    pub fn cast_sphere_down(&self, origin: Vec3, radius: f32, max_distance: f32, exclude: Entity) -> Option<ShapeHit> {
+        let options = ShapeCastOptions {
+            max_time_of_impact: max_distance,
+            target_distance: 0.0,
+            stop_at_penetration: false,
+            compute_impact_geometry_on_penetration: true,
+        };
+        let filter = QueryFilter::default()
+            .exclude_sensors()
+            .exclude_rigid_body(exclude);
+
+        self.context.cast_shape(origin, Quat::IDENTITY, Vec3::NEG_Y, &Collider::ball(radius), options, filter)
+            .map(|(entity, hit)| ShapeHit {
+                entity,
+                distance: hit.time_of_impact,
+                // The first shape of the hit is the collider which was hit, his normal is in world space.
+                normal: hit.details.map_or(Vec3::Y, |details| details.normal1),
+            })
+    }
+
+    /// Cast a box with `half_extents` and `rotation` along `direction` and
+    /// ignore the collider of `exclude` and all sensors.
+    pub fn cast_box(&self, origin: Vec3, half_extents: Vec3, rotation: Quat, direction: Vec3, max_distance: f32, exclude: Entity) -> Option<ShapeHit> {
+        let options = ShapeCastOptions {
+            max_time_of_impact: max_distance,
+            target_distance: 0.0,
+            stop_at_penetration: false,
+            compute_impact_geometry_on_penetration: true,
+        };
+        let filter = QueryFilter::default()
+            .exclude_sensors()
+            .exclude_rigid_body(exclude);
+        let shape = Collider::cuboid(half_extents.x, half_extents.y, half_extents.z);
+        let direction = direction.normalize_or_zero();
+
+        self.context.cast_shape(origin, rotation, direction, &shape, options, filter)
+            .map(|(entity, hit)| ShapeHit {
+                entity,
+                distance: hit.time_of_impact,
+                normal: hit.details.map_or(-direction, |details| details.normal1),
+            })
+    }
+}
+
+/// Create the dynamic body of the player with a box collider.
+pub fn player_body(half_extents: Vec3) -> impl Bundle {
+    (
+        RigidBody::Dynamic,
+        player_collider(half_extents),
+        Velocity::default(),
+        Damping {
+            linear_damping: 0.2,
+            angular_damping: 0.5, // stop random rotating.
+        },
+        LockedAxes::ROTATION_LOCKED_X | LockedAxes::ROTATION_LOCKED_Z,
+    )
+}
+
+/// Create the box collider of the player. Insert it again to resize the player.
+pub fn player_collider(half_extents: Vec3) -> impl Bundle {
+    Collider::cuboid(half_extents.x, half_extents.y, half_extents.z)
+}
+
+/// Create a fixed body with a trimesh collider build from the render mesh.
+/// Returns [`None`] if the mesh has no usable positions or indices.
+pub fn terrain_body(mesh: &Mesh) -> Option<impl Bundle> {
+    Collider::from_bevy_mesh(mesh, &ComputedColliderShape::TriMesh)
+        .map(|collider| (RigidBody::Fixed, collider))
+}
+
+/// Create a fixed body from a [`TerrainCollider`] which was baked before,
+/// so the render mesh is not read again.
+pub fn baked_terrain_body(collider: &TerrainCollider) -> impl Bundle {
+    (RigidBody::Fixed, Collider::trimesh(collider.vertices.clone(), collider.indices.clone()))
+}
+
+/// Create a fixed body with a heightfield collider. Ray casts against it are
+/// cheaper than against a trimesh of the same grid.
+pub fn heightfield_body(heightfield: &TerrainHeightfield) -> impl Bundle {
+    // Rapier reads the heights column by column, his rows run along z.
+    let heights = (0..heightfield.columns)
+        .flat_map(|column| (0..heightfield.rows).map(move |row| heightfield.heights[row * heightfield.columns + column]))
+        .collect();
+    let scale = Vec3::new(heightfield.size.x, 1.0, heightfield.size.y);
+
+    (RigidBody::Fixed, Collider::heightfield(heights, heightfield.rows, heightfield.columns, scale))
+}
+
+/// Remove the body of [`terrain_body`], [`baked_terrain_body`] or [`heightfield_body`], the entity stays.
+pub fn remove_terrain_body(commands: &mut Commands, entity: Entity) {
+    commands.entity(entity).remove::<(RigidBody, Collider)>();
+}
+
+/// Switch a dynamic body to kinematic and back. Kinematic bodies are moved
+/// only over their [`Transform`].
+pub fn set_body_kinematic(commands: &mut Commands, entity: Entity, kinematic: bool) {
+    if kinematic {
+        commands.entity(entity).insert(RigidBody::KinematicPositionBased);
+    } else {
+        commands.entity(entity).insert(RigidBody::Dynamic);
+    }
+}
+
+/// Enable or disable the collider of an entity without removing it.
+pub fn set_collider_enabled(commands: &mut Commands, entity: Entity, enabled: bool) {
+    if enabled {
+        commands.entity(entity).remove::<ColliderDisabled>();
+    } else {
+        commands.entity(entity).insert(ColliderDisabled);
+    }
+}
+
+fn plugin_init_rapier3d_debug() -> RapierDebugRenderPlugin {
+    RapierDebugRenderPlugin {
+        enabled: true,
+        style: DebugRenderStyle {
+            collider_parentless_color: [0.0, 1.0, 1.0, 1.0],
+            collider_dynamic_color: [305.0, 1.0, 0.5, 1.0],
+            collider_fixed_color: [65.0, 1.0, 0.5, 1.0],
+            collider_kinematic_color: [140.0, 1.0, 0.5, 1.0],
+            sleep_color_multiplier: [0.0, 0.5, 0.5, 1.0],
+            ..default()
+        },
+        ..default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if the sphere cast gives the normal of the ground
+    /// surface, which points up.
+    #[test]
+    fn test_cast_sphere_down_ground_normal() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, TransformPlugin, HierarchyPlugin))
+            .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<SceneSpawner>();
+        app.world_mut().spawn((
+            TransformBundle::default(),
+            RigidBody::Fixed,
+            Collider::cuboid(4.0, 0.1, 4.0),
+        ));
+        let player = app.world_mut().spawn((
+            TransformBundle::from_transform(Transform::from_xyz(0.0, 1.0, 0.0)),
+            player_body(Vec3::splat(0.5)),
+        )).id();
+        app.update();
+        app.update();
+
+        let mut system_state = bevy::ecs::system::SystemState::<PhysicsQuery>::new(app.world_mut());
+        let physics_query = system_state.get(app.world());
+        let hit = physics_query.cast_sphere_down(Vec3::new(0.0, 1.0, 0.0), 0.2, 2.0, player).unwrap();
+
+        assert!((hit.distance - 0.7).abs() < 0.01);
+        assert!(hit.normal.y > 0.99);
+    }
+
+    /// Unit Test for check if the box cast hits a ceiling edge beside his
+    /// center, which a ray from the center would miss.
+    #[test]
+    fn test_cast_box_hits_ceiling_edge() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, TransformPlugin, HierarchyPlugin))
+            .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<SceneSpawner>();
+        app.world_mut().spawn((
+            TransformBundle::from_transform(Transform::from_xyz(1.4, 2.0, 0.0)),
+            RigidBody::Fixed,
+            Collider::cuboid(1.0, 0.1, 1.0),
+        ));
+        let player = app.world_mut().spawn((
+            TransformBundle::from_transform(Transform::from_xyz(0.0, 1.0, 0.0)),
+            player_body(Vec3::splat(0.5)),
+        )).id();
+        app.update();
+        app.update();
+
+        let mut system_state = bevy::ecs::system::SystemState::<PhysicsQuery>::new(app.world_mut());
+        let physics_query = system_state.get(app.world());
+        let origin = Vec3::new(0.0, 1.5, 0.0);
+
+        let hit = physics_query.cast_box(origin, Vec3::new(0.5, 0.05, 0.5), Quat::IDENTITY, Vec3::Y, 1.0, player).unwrap();
+        assert!((hit.distance - 0.35).abs() < 0.01);
+        assert!(hit.normal.y < -0.99);
+    }
+}
@@ -0,0 +1,171 @@
+use bevy::ecs::query::QueryData;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+use crate::manager::{in_game, AppState};
+use crate::physics::{ShapeHit, TerrainCollider, TerrainHeightfield};
+
+/// Name of the backend, used for logs and the benchmark output.
+pub const BACKEND_NAME: &str = "xpbd";
+
+/// Plugin which adds the xpbd simulation and his debug renderer.
+pub struct PhysicsBackendPlugin;
+
+impl Plugin for PhysicsBackendPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(PhysicsPlugins::default())
+            .add_plugins(PhysicsDebugPlugin::default());
+
+        // Bodies would fall through the chunks which are not streamed in yet.
+        app.add_systems(Update, update_simulation_active.run_if(state_changed::<AppState>));
+
+        info!("Physics backend: {}", BACKEND_NAME);
+    }
+}
+
+/// Run the simulation only in game, it is paused behind the loading screen.
+fn update_simulation_active(state: Res<State<AppState>>,
+                            mut time: ResMut<Time<Physics>>
+) {
+    if in_game(state) {
+        time.unpause();
+    } else {
+        time.pause();
+    }
+}
+
+/// Velocity access of a rigid body. Use it inside a [`Query`] like
+/// `Query<BodyVelocity>` and change the values over the item functions.
+#[derive(QueryData)]
+#[query_data(mutable)]
+pub struct BodyVelocity {
+    linear: &'static mut LinearVelocity,
+    angular: &'static mut AngularVelocity,
+}
+
+impl BodyVelocityItem<'_> {
+    pub fn linvel(&self) -> Vec3 {
+        self.linear.0
+    }
+
+    pub fn set_linvel(&mut self, linvel: Vec3) {
+        self.linear.0 = linvel;
+    }
+
+    pub fn set_angvel(&mut self, angvel: Vec3) {
+        self.angular.0 = angvel;
+    }
+}
+
+impl BodyVelocityReadOnlyItem<'_> {
+    pub fn linvel(&self) -> Vec3 {
+        self.linear.0
+    }
+}
+
+/// [`SystemParam`] for scene queries like shape casts.
+#[derive(SystemParam)]
+pub struct PhysicsQuery<'w, 's> {
+    spatial_query: SpatialQuery<'w, 's>,
+}
+
+impl PhysicsQuery<'_, '_> {
+    /// Cast a sphere straight down and ignore the collider of `exclude`.
+    /// Hits static, kinematic and dynamic bodies.
+    pub fn cast_sphere_down(&self, origin: Vec3, radius: f32, max_distance: f32, exclude: Entity) -> Option<ShapeHit> {
+        let filter = SpatialQueryFilter::default().with_excluded_entities([exclude]);
+
+        self.spatial_query.cast_shape(
+            &Collider::sphere(radius), origin, Quat::IDENTITY, Dir3::NEG_Y, max_distance, false, filter,
+        ).map(|hit| ShapeHit {
+            entity: hit.entity,
+            distance: hit.time_of_impact,
+            // The first shape of the hit is the collider which was hit, his normal is in world space.
+            normal: hit.normal1,
+        })
+    }
+
+    /// Cast a box with `half_extents` and `rotation` along `direction` and
+    /// ignore the collider of `exclude`.
+    pub fn cast_box(&self, origin: Vec3, half_extents: Vec3, rotation: Quat, direction: Vec3, max_distance: f32, exclude: Entity) -> Option<ShapeHit> {
+        let direction = Dir3::new(direction).ok()?;
+        let filter = SpatialQueryFilter::default().with_excluded_entities([exclude]);
+        let shape = Collider::cuboid(half_extents.x * 2.0, half_extents.y * 2.0, half_extents.z * 2.0);
+
+        self.spatial_query.cast_shape(
+            &shape, origin, rotation, direction, max_distance, false, filter,
+        ).map(|hit| ShapeHit {
+            entity: hit.entity,
+            distance: hit.time_of_impact,
+            normal: hit.normal1,
+        })
+    }
+}
+
+/// Create the dynamic body of the player with a box collider.
+pub fn player_body(half_extents: Vec3) -> impl Bundle {
+    (
+        RigidBody::Dynamic,
+        player_collider(half_extents),
+        LinearVelocity::default(),
+        AngularVelocity::default(),
+        LinearDamping(0.2),
+        AngularDamping(0.5), // stop random rotating.
+        LockedAxes::new().lock_rotation_x().lock_rotation_z(),
+    )
+}
+
+/// Create the box collider of the player. Insert it again to resize the player.
+pub fn player_collider(half_extents: Vec3) -> impl Bundle {
+    Collider::cuboid(half_extents.x * 2.0, half_extents.y * 2.0, half_extents.z * 2.0)
+}
+
+/// Create a static body with a trimesh collider build from the render mesh.
+/// Returns [`None`] if the mesh has no usable positions or indices.
+pub fn terrain_body(mesh: &Mesh) -> Option<impl Bundle> {
+    Collider::trimesh_from_mesh(mesh)
+        .map(|collider| (RigidBody::Static, collider))
+}
+
+/// Create a static body from a [`TerrainCollider`] which was baked before,
+/// so the render mesh is not read again.
+pub fn baked_terrain_body(collider: &TerrainCollider) -> impl Bundle {
+    (RigidBody::Static, Collider::trimesh(collider.vertices.clone(), collider.indices.clone()))
+}
+
+/// Create a static body with a heightfield collider. Ray casts against it are
+/// cheaper than against a trimesh of the same grid.
+pub fn heightfield_body(heightfield: &TerrainHeightfield) -> impl Bundle {
+    // XPBD takes one vec per step along x with the heights along z.
+    let heights = (0..heightfield.columns)
+        .map(|column| (0..heightfield.rows).map(|row| heightfield.heights[row * heightfield.columns + column]).collect())
+        .collect();
+    let scale = Vec3::new(heightfield.size.x, 1.0, heightfield.size.y);
+
+    (RigidBody::Static, Collider::heightfield(heights, scale))
+}
+
+/// Remove the body of [`terrain_body`], [`baked_terrain_body`] or [`heightfield_body`], the entity stays.
+pub fn remove_terrain_body(commands: &mut Commands, entity: Entity) {
+    commands.entity(entity).remove::<(RigidBody, Collider)>();
+}
+
+/// Switch a dynamic body to kinematic and back. Kinematic bodies are moved
+/// only over their [`Transform`].
+pub fn set_body_kinematic(commands: &mut Commands, entity: Entity, kinematic: bool) {
+    if kinematic {
+        commands.entity(entity).insert(RigidBody::Kinematic);
+    } else {
+        commands.entity(entity).insert(RigidBody::Dynamic);
+    }
+}
+
+/// Enable or disable the collider of an entity without removing it.
+/// xpbd has no disabled marker, so the collider leaves all collision layers.
+pub fn set_collider_enabled(commands: &mut Commands, entity: Entity, enabled: bool) {
+    if enabled {
+        commands.entity(entity).insert(CollisionLayers::default());
+    } else {
+        commands.entity(entity).insert(CollisionLayers::NONE);
+    }
+}
@@ -0,0 +1,240 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use bevy::prelude::*;
+use gltf::buffer::Source;
+use gltf::mesh::Mode;
+use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+//################################################# Models #################################################
+/// Vertex of a terrain triangle in world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerrainVertex {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub uv: Vec2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerrainTriangle {
+    pub vertices: [TerrainVertex; 3],
+    /// Index into [`SourceArea::materials`], `None` for the default material.
+    pub material: Option<usize>,
+}
+
+/// Factors and textures of a glb material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainMaterial {
+    pub name: Option<String>,
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub base_color_texture: Option<TerrainTexture>,
+    pub metallic_roughness_texture: Option<TerrainTexture>,
+    pub normal_texture: Option<TerrainTexture>,
+}
+
+/// Texture of a [`TerrainMaterial`] with the sampler of the source glb.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainTexture {
+    /// Index into [`SourceArea::images`].
+    pub image: usize,
+    pub tex_coord: u32,
+    pub mag_filter: Option<MagFilter>,
+    pub min_filter: Option<MinFilter>,
+    pub wrap: [WrappingMode; 2],
+}
+
+/// Image of a texture. The chunk files reference it by [`TerrainImage::uri`],
+/// relative to the output directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainImage {
+    pub uri: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Monolithic area glb like `maps/debug.glb`. Meshes of nodes with `terrain`
+/// in their name are read as triangles, every other named node is kept as
+/// gameplay node with his world transform.
+#[derive(Debug, Default)]
+pub struct SourceArea {
+    pub triangles: Vec<TerrainTriangle>,
+    pub materials: Vec<TerrainMaterial>,
+    pub images: Vec<TerrainImage>,
+    pub nodes: Vec<(String, Transform)>,
+}
+
+//################################################# Implementations #################################################
+impl SourceArea {
+    pub fn read(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let gltf = gltf::Gltf::open(path)?;
+        let buffers = gltf.buffers()
+            .map(|buffer| match buffer.source() {
+                Source::Bin => gltf.blob.clone().ok_or_else(|| Box::<dyn Error>::from("the glb has no binary chunk")),
+                Source::Uri(uri) => fs::read(path.with_file_name(uri)).map_err(Box::<dyn Error>::from),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let scene = gltf.default_scene()
+            .or_else(|| gltf.scenes().next())
+            .ok_or("the glb has no scene")?;
+
+        let images = gltf.images()
+            .map(|image| TerrainImage::read(&image, path, &buffers))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut area = SourceArea {
+            materials: gltf.materials().map(TerrainMaterial::from).collect(),
+            images,
+            ..default()
+        };
+        for node in scene.nodes() {
+            area.read_node(&node, Mat4::IDENTITY, &buffers);
+        }
+
+        Ok(area)
+    }
+
+    fn read_node(&mut self, node: &gltf::Node, parent: Mat4, buffers: &[Vec<u8>]) {
+        let transform = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+        let name = node.name().unwrap_or_default();
+
+        if name.contains("terrain") {
+            if let Some(mesh) = node.mesh() {
+                self.read_terrain(&mesh, transform, buffers);
+            }
+        } else if !name.is_empty() {
+            self.nodes.push((name.to_string(), Transform::from_matrix(transform)));
+        }
+
+        for child in node.children() {
+            self.read_node(&child, transform, buffers);
+        }
+    }
+
+    fn read_terrain(&mut self, mesh: &gltf::Mesh, transform: Mat4, buffers: &[Vec<u8>]) {
+        let normal_matrix = Mat3::from_mat4(transform).inverse().transpose();
+
+        for primitive in mesh.primitives().filter(|primitive| primitive.mode() == Mode::Triangles) {
+            let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(Vec::as_slice));
+            let Some(positions) = reader.read_positions() else {
+                continue;
+            };
+
+            let positions = positions.map(|position| transform.transform_point3(Vec3::from(position))).collect::<Vec<_>>();
+            let normals = reader.read_normals()
+                .map(|normals| normals.map(|normal| (normal_matrix * Vec3::from(normal)).normalize_or_zero()).collect::<Vec<_>>())
+                .unwrap_or_default();
+            let uvs = reader.read_tex_coords(0)
+                .map(|uvs| uvs.into_f32().map(Vec2::from).collect::<Vec<_>>())
+                .unwrap_or_default();
+            let indices = reader.read_indices()
+                .map(|indices| indices.into_u32().collect::<Vec<_>>())
+                .unwrap_or_else(|| (0..positions.len() as u32).collect());
+
+            let vertex = |index: u32| {
+                let index = index as usize;
+                TerrainVertex {
+                    position: positions[index],
+                    normal: normals.get(index).copied().unwrap_or(Vec3::Y),
+                    uv: uvs.get(index).copied().unwrap_or(Vec2::ZERO),
+                }
+            };
+
+            for triangle in indices.chunks_exact(3) {
+                self.triangles.push(TerrainTriangle {
+                    vertices: [vertex(triangle[0]), vertex(triangle[1]), vertex(triangle[2])],
+                    material: primitive.material().index(),
+                });
+            }
+        }
+    }
+}
+
+impl<'a> From<gltf::Material<'a>> for TerrainMaterial {
+    fn from(material: gltf::Material<'a>) -> Self {
+        let pbr = material.pbr_metallic_roughness();
+        Self {
+            name: material.name().map(str::to_string),
+            base_color: pbr.base_color_factor(),
+            metallic: pbr.metallic_factor(),
+            roughness: pbr.roughness_factor(),
+            base_color_texture: pbr.base_color_texture().map(|info| TerrainTexture::from(info.texture()).with_tex_coord(info.tex_coord())),
+            metallic_roughness_texture: pbr.metallic_roughness_texture().map(|info| TerrainTexture::from(info.texture()).with_tex_coord(info.tex_coord())),
+            normal_texture: material.normal_texture().map(|normal| TerrainTexture::from(normal.texture()).with_tex_coord(normal.tex_coord())),
+        }
+    }
+}
+
+impl Default for TerrainMaterial {
+    /// Material of terrain without material in the source glb, a rough
+    /// white surface instead of the metallic glTF default.
+    fn default() -> Self {
+        Self {
+            name: Some("default".to_string()),
+            base_color: [1.0; 4],
+            metallic: 0.0,
+            roughness: 1.0,
+            base_color_texture: None,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+        }
+    }
+}
+
+impl TerrainMaterial {
+    pub fn textures(&self) -> impl Iterator<Item = &TerrainTexture> {
+        self.base_color_texture.iter()
+            .chain(self.metallic_roughness_texture.iter())
+            .chain(self.normal_texture.iter())
+    }
+}
+
+impl TerrainTexture {
+    fn with_tex_coord(self, tex_coord: u32) -> Self {
+        Self { tex_coord, ..self }
+    }
+}
+
+impl<'a> From<gltf::Texture<'a>> for TerrainTexture {
+    fn from(texture: gltf::Texture<'a>) -> Self {
+        let sampler = texture.sampler();
+        Self {
+            image: texture.source().index(),
+            tex_coord: 0,
+            mag_filter: sampler.mag_filter(),
+            min_filter: sampler.min_filter(),
+            wrap: [sampler.wrap_s(), sampler.wrap_t()],
+        }
+    }
+}
+
+impl TerrainImage {
+    /// Read the image from the binary chunk or from the file next to `path`.
+    /// The uri is `textures/<index>_<name>` with only url safe characters,
+    /// the index keeps images with the same name apart.
+    fn read(image: &gltf::Image, path: &Path, buffers: &[Vec<u8>]) -> Result<Self, Box<dyn Error>> {
+        let (bytes, file_name) = match image.source() {
+            gltf::image::Source::View { view, mime_type } => {
+                let buffer = buffers.get(view.buffer().index()).ok_or("an image view has no buffer")?;
+                let bytes = buffer.get(view.offset()..view.offset() + view.length()).ok_or("an image view is out of his buffer")?;
+                let extension = mime_type.strip_prefix("image/").unwrap_or("bin").replace("jpeg", "jpg");
+                (bytes.to_vec(), format!("{}.{}", image.name().unwrap_or("image"), extension))
+            }
+            gltf::image::Source::Uri { uri, .. } => {
+                let bytes = fs::read(path.with_file_name(uri))?;
+                let file_name = Path::new(uri).file_name().ok_or("an image uri has no file name")?;
+                (bytes, file_name.to_string_lossy().to_string())
+            }
+        };
+
+        let file_name = file_name.chars()
+            .map(|character| if character.is_ascii_alphanumeric() || "._-".contains(character) { character } else { '_' })
+            .collect::<String>();
+
+        Ok(Self {
+            uri: format!("textures/{}_{}", image.index(), file_name),
+            bytes,
+        })
+    }
+}
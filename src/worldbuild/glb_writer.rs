@@ -0,0 +1,373 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use bevy::prelude::*;
+use gltf::binary::{Glb, Header};
+use gltf::json;
+use gltf::json::validation::Checked::Valid;
+use gltf::json::validation::USize64;
+use crate::area_source::{TerrainImage, TerrainMaterial, TerrainTexture};
+
+//################################################# Models #################################################
+/// Mesh of a [`GlbNode`], his primitives share the vertices. Empty normals
+/// or uvs are not written.
+#[derive(Debug, Default)]
+pub struct GlbMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub primitives: Vec<GlbPrimitive>,
+}
+
+#[derive(Debug, Default)]
+pub struct GlbPrimitive {
+    pub indices: Vec<u32>,
+    pub material: Option<TerrainMaterial>,
+}
+
+#[derive(Debug)]
+pub struct GlbNode {
+    pub name: String,
+    pub transform: Transform,
+    pub mesh: Option<GlbMesh>,
+}
+
+/// Collects the json and the binary chunk of a glb. Materials and textures
+/// used by several primitives are written once, images are referenced by
+/// their uri.
+struct GlbBuilder<'a> {
+    root: json::Root,
+    bin: Vec<u8>,
+    buffer: json::Index<json::Buffer>,
+    images: &'a [TerrainImage],
+    materials: Vec<(TerrainMaterial, json::Index<json::Material>)>,
+    textures: Vec<(TerrainTexture, json::Index<json::Texture>)>,
+    image_indices: HashMap<usize, json::Index<json::Image>>,
+}
+
+//################################################# Implementations #################################################
+impl<'a> GlbBuilder<'a> {
+    fn new(images: &'a [TerrainImage]) -> Self {
+        let mut root = json::Root::default();
+        let buffer = root.push(json::Buffer {
+            byte_length: USize64(0),
+            extensions: Default::default(),
+            extras: Default::default(),
+            name: None,
+            uri: None,
+        });
+
+        Self {
+            root,
+            bin: Vec::new(),
+            buffer,
+            images,
+            materials: Vec::new(),
+            textures: Vec::new(),
+            image_indices: HashMap::new(),
+        }
+    }
+
+    fn push_view(&mut self, bytes: Vec<u8>, target: json::buffer::Target) -> json::Index<json::buffer::View> {
+        // Every view starts 4 byte aligned, as required for f32 and u32 data.
+        self.bin.resize(self.bin.len().next_multiple_of(4), 0);
+        let offset = self.bin.len();
+        self.bin.extend(bytes);
+
+        self.root.push(json::buffer::View {
+            buffer: self.buffer,
+            byte_length: USize64::from(self.bin.len() - offset),
+            byte_offset: Some(USize64::from(offset)),
+            byte_stride: None,
+            extensions: Default::default(),
+            extras: Default::default(),
+            name: None,
+            target: Some(Valid(target)),
+        })
+    }
+
+    fn push_accessor<const N: usize>(&mut self,
+                                     values: &[[f32; N]],
+                                     type_: json::accessor::Type,
+                                     bounds: bool
+    ) -> json::Index<json::Accessor> {
+        let bytes = values.iter().flatten().flat_map(|value| value.to_le_bytes()).collect();
+        let view = self.push_view(bytes, json::buffer::Target::ArrayBuffer);
+
+        // Positions need min and max, see the glTF specification.
+        let (min, max) = if bounds {
+            let min = (0..N).map(|i| values.iter().map(|value| value[i]).fold(f32::MAX, f32::min)).collect::<Vec<_>>();
+            let max = (0..N).map(|i| values.iter().map(|value| value[i]).fold(f32::MIN, f32::max)).collect::<Vec<_>>();
+            (Some(json::Value::from(min)), Some(json::Value::from(max)))
+        } else {
+            (None, None)
+        };
+
+        self.root.push(json::Accessor {
+            buffer_view: Some(view),
+            byte_offset: Some(USize64(0)),
+            count: USize64::from(values.len()),
+            component_type: Valid(json::accessor::GenericComponentType(json::accessor::ComponentType::F32)),
+            extensions: Default::default(),
+            extras: Default::default(),
+            type_: Valid(type_),
+            min,
+            max,
+            name: None,
+            normalized: false,
+            sparse: None,
+        })
+    }
+
+    fn push_indices(&mut self, indices: &[u32]) -> json::Index<json::Accessor> {
+        let bytes = indices.iter().flat_map(|index| index.to_le_bytes()).collect();
+        let view = self.push_view(bytes, json::buffer::Target::ElementArrayBuffer);
+
+        self.root.push(json::Accessor {
+            buffer_view: Some(view),
+            byte_offset: Some(USize64(0)),
+            count: USize64::from(indices.len()),
+            component_type: Valid(json::accessor::GenericComponentType(json::accessor::ComponentType::U32)),
+            extensions: Default::default(),
+            extras: Default::default(),
+            type_: Valid(json::accessor::Type::Scalar),
+            min: None,
+            max: None,
+            name: None,
+            normalized: false,
+            sparse: None,
+        })
+    }
+
+    fn push_material(&mut self, material: &TerrainMaterial) -> Result<json::Index<json::Material>, Box<dyn Error>> {
+        if let Some((_, index)) = self.materials.iter().find(|(written, _)| written == material) {
+            return Ok(*index);
+        }
+
+        let texture_info = |builder: &mut Self, texture: &TerrainTexture| builder.push_texture(texture)
+            .map(|index| json::texture::Info {
+                index,
+                tex_coord: texture.tex_coord,
+                extensions: Default::default(),
+                extras: Default::default(),
+            });
+
+        let base_color_texture = material.base_color_texture.as_ref().map(|texture| texture_info(self, texture)).transpose()?;
+        let metallic_roughness_texture = material.metallic_roughness_texture.as_ref().map(|texture| texture_info(self, texture)).transpose()?;
+        let normal_texture = material.normal_texture.as_ref()
+            .map(|texture| self.push_texture(texture).map(|index| json::material::NormalTexture {
+                index,
+                scale: 1.0,
+                tex_coord: texture.tex_coord,
+                extensions: Default::default(),
+                extras: Default::default(),
+            }))
+            .transpose()?;
+
+        let index = self.root.push(json::Material {
+            name: material.name.clone(),
+            pbr_metallic_roughness: json::material::PbrMetallicRoughness {
+                base_color_factor: json::material::PbrBaseColorFactor(material.base_color),
+                base_color_texture,
+                metallic_factor: json::material::StrengthFactor(material.metallic),
+                roughness_factor: json::material::StrengthFactor(material.roughness),
+                metallic_roughness_texture,
+                ..Default::default()
+            },
+            normal_texture,
+            ..Default::default()
+        });
+
+        self.materials.push((material.clone(), index));
+        Ok(index)
+    }
+
+    fn push_texture(&mut self, texture: &TerrainTexture) -> Result<json::Index<json::Texture>, Box<dyn Error>> {
+        if let Some((_, index)) = self.textures.iter().find(|(written, _)| written == texture) {
+            return Ok(*index);
+        }
+
+        let image = match self.image_indices.get(&texture.image) {
+            Some(image) => *image,
+            None => {
+                let uri = &self.images.get(texture.image).ok_or("a texture has no image")?.uri;
+                let image = self.root.push(json::Image {
+                    buffer_view: None,
+                    mime_type: None,
+                    name: None,
+                    uri: Some(uri.clone()),
+                    extensions: Default::default(),
+                    extras: Default::default(),
+                });
+                self.image_indices.insert(texture.image, image);
+                image
+            }
+        };
+
+        let sampler = self.root.push(json::texture::Sampler {
+            mag_filter: texture.mag_filter.map(Valid),
+            min_filter: texture.min_filter.map(Valid),
+            wrap_s: Valid(texture.wrap[0]),
+            wrap_t: Valid(texture.wrap[1]),
+            ..Default::default()
+        });
+
+        let index = self.root.push(json::Texture {
+            name: None,
+            sampler: Some(sampler),
+            source: image,
+            extensions: Default::default(),
+            extras: Default::default(),
+        });
+
+        self.textures.push((texture.clone(), index));
+        Ok(index)
+    }
+
+    fn push_mesh(&mut self, name: &str, mesh: &GlbMesh) -> Result<json::Index<json::Mesh>, Box<dyn Error>> {
+        let mut attributes = BTreeMap::new();
+        attributes.insert(Valid(json::mesh::Semantic::Positions), self.push_accessor(&mesh.positions, json::accessor::Type::Vec3, true));
+        if !mesh.normals.is_empty() {
+            attributes.insert(Valid(json::mesh::Semantic::Normals), self.push_accessor(&mesh.normals, json::accessor::Type::Vec3, false));
+        }
+        if !mesh.uvs.is_empty() {
+            attributes.insert(Valid(json::mesh::Semantic::TexCoords(0)), self.push_accessor(&mesh.uvs, json::accessor::Type::Vec2, false));
+        }
+
+        let mut primitives = Vec::new();
+        for primitive in mesh.primitives.iter() {
+            primitives.push(json::mesh::Primitive {
+                attributes: attributes.clone(),
+                extensions: Default::default(),
+                extras: Default::default(),
+                indices: Some(self.push_indices(&primitive.indices)),
+                material: primitive.material.as_ref().map(|material| self.push_material(material)).transpose()?,
+                mode: Valid(json::mesh::Mode::Triangles),
+                targets: None,
+            });
+        }
+
+        Ok(self.root.push(json::Mesh {
+            extensions: Default::default(),
+            extras: Default::default(),
+            name: Some(name.to_string()),
+            primitives,
+            weights: None,
+        }))
+    }
+
+    fn push_node(&mut self, node: &GlbNode) -> Result<json::Index<json::Node>, Box<dyn Error>> {
+        let mesh = node.mesh.as_ref().map(|mesh| self.push_mesh(&node.name, mesh)).transpose()?;
+        let rotation = node.transform.rotation;
+
+        Ok(self.root.push(json::Node {
+            name: Some(node.name.clone()),
+            mesh,
+            translation: Some(node.transform.translation.to_array()),
+            rotation: Some(json::scene::UnitQuaternion([rotation.x, rotation.y, rotation.z, rotation.w])),
+            scale: Some(node.transform.scale.to_array()),
+            ..Default::default()
+        }))
+    }
+
+    fn build(mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.root.buffers[0].byte_length = USize64::from(self.bin.len());
+        let json = json::serialize::to_vec(&self.root)?;
+
+        let length = 12 + 8 + json.len().next_multiple_of(4) + 8 + self.bin.len().next_multiple_of(4);
+        let glb = Glb {
+            header: Header {
+                magic: *b"glTF",
+                version: 2,
+                length: u32::try_from(length)?,
+            },
+            json: Cow::Owned(json),
+            bin: Some(Cow::Owned(self.bin)),
+        };
+
+        let mut bytes = Vec::new();
+        glb.to_writer(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+/// Glb with `nodes` as one scene, nodes are not nested. The textures of the
+/// materials reference `images` by their uri, the images are not embedded.
+pub fn glb_bytes(nodes: &[GlbNode], images: &[TerrainImage]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut builder = GlbBuilder::new(images);
+    let scene_nodes = nodes.iter().map(|node| builder.push_node(node)).collect::<Result<_, _>>()?;
+
+    let scene = builder.root.push(json::Scene {
+        extensions: Default::default(),
+        extras: Default::default(),
+        name: None,
+        nodes: scene_nodes,
+    });
+    builder.root.scene = Some(scene);
+
+    builder.build()
+}
+
+pub fn write_glb(path: &Path, nodes: &[GlbNode], images: &[TerrainImage]) -> Result<(), Box<dyn Error>> {
+    fs::write(path, glb_bytes(nodes, images)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit Test for check if a written glb can be read again with his node transform
+    /// and one primitive per material, which references his texture image.
+    #[test]
+    fn test_glb_roundtrip() {
+        let images = [TerrainImage {
+            uri: "textures/0_grass.png".to_string(),
+            bytes: Vec::new(),
+        }];
+        let grass = TerrainMaterial {
+            name: Some("grass".to_string()),
+            base_color_texture: Some(TerrainTexture {
+                image: 0,
+                tex_coord: 0,
+                mag_filter: None,
+                min_filter: None,
+                wrap: [gltf::texture::WrappingMode::Repeat; 2],
+            }),
+            ..default()
+        };
+
+        let nodes = [GlbNode {
+            name: "terrain".to_string(),
+            transform: Transform::from_xyz(512.0, 0.0, -512.0),
+            mesh: Some(GlbMesh {
+                positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 1.0]],
+                normals: vec![[0.0, 1.0, 0.0]; 4],
+                primitives: vec![
+                    GlbPrimitive { indices: vec![0, 2, 1], material: Some(TerrainMaterial::default()) },
+                    GlbPrimitive { indices: vec![1, 2, 3], material: Some(grass) },
+                ],
+                ..default()
+            }),
+        }];
+
+        let bytes = glb_bytes(&nodes, &images).unwrap();
+        let gltf = gltf::Gltf::from_slice(&bytes).unwrap();
+        let node = gltf.nodes().next().unwrap();
+        let primitives = node.mesh().unwrap().primitives().collect::<Vec<_>>();
+        let reader = primitives[0].reader(|_| gltf.blob.as_deref());
+
+        assert_eq!(node.name(), Some("terrain"));
+        assert_eq!(node.transform().decomposed().0, [512.0, 0.0, -512.0]);
+        assert_eq!(reader.read_positions().unwrap().count(), 4);
+        assert_eq!(reader.read_indices().unwrap().into_u32().collect::<Vec<_>>(), vec![0, 2, 1]);
+        assert!(reader.read_tex_coords(0).is_none());
+
+        assert_eq!(primitives.len(), 2);
+        assert_eq!(primitives[0].material().name(), Some("default"));
+        let texture = primitives[1].material().pbr_metallic_roughness().base_color_texture().unwrap();
+        assert!(matches!(texture.texture().source().source(), gltf::image::Source::Uri { uri: "textures/0_grass.png", .. }));
+    }
+}
@@ -0,0 +1,163 @@
+//! World build tool, splits a monolithic area glb into one glb per chunk
+//! and writes the area index the game streams them with.
+//!
+//! `cargo run --bin mira-worldbuild -- assets/maps/debug.glb --output assets/maps/debug`
+
+mod area_source;
+mod glb_writer;
+mod terrain_tiles;
+
+#[path = "../environment/area_index.rs"]
+#[allow(dead_code)]
+mod area_index;
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use bevy::prelude::*;
+use clap::Parser;
+use crate::area_index::{AreaIndex, AreaIndexChunk};
+use crate::area_source::{SourceArea, TerrainMaterial};
+use crate::glb_writer::{write_glb, GlbMesh, GlbNode, GlbPrimitive};
+use crate::terrain_tiles::{slice_terrain, TerrainTile};
+
+/// Chunk size of the game, has to match the chunk size of the chunk handler.
+const DEFAULT_CHUNK_SIZE: i32 = 512;
+
+//################################################# Models #################################################
+/// Command line arguments of the world build tool, see `mira-worldbuild --help`.
+#[derive(Parser, Debug, Clone, PartialEq)]
+#[command(name = "mira-worldbuild", version, about = "Split an area glb into chunk glb files and an area index")]
+struct WorldBuildCli {
+    /// Area glb exported from Blender.
+    #[arg(value_name = "FILE")]
+    input: PathBuf,
+
+    /// Directory for the chunk files and the area index.
+    #[arg(long, value_name = "DIR")]
+    output: PathBuf,
+
+    /// Name of the area, the file name of the input by default.
+    #[arg(long)]
+    name: Option<String>,
+
+    /// Edge length of a chunk.
+    #[arg(long, default_value_t = DEFAULT_CHUNK_SIZE, value_parser = clap::value_parser!(i32).range(1..))]
+    chunk_size: i32,
+
+    /// Count of decimated levels of detail next to the full terrain mesh.
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u32).range(0..=4))]
+    lod_levels: u32,
+}
+
+//################################################# Implementations #################################################
+fn main() -> ExitCode {
+    let cli = WorldBuildCli::parse();
+
+    match build_area(&cli) {
+        Ok(index) => {
+            println!("Wrote {} chunks of area {:?} into {}", index.chunks.len(), index.name, cli.output.display());
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("error: failed to build {}: {}", cli.input.display(), error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Write every terrain tile as `chunk_x_z.glb` with a `terrain` node, the
+/// decimated `terrain_lod<n>` nodes and the precomputed collision mesh as
+/// `terrain_col` node. Every material is one primitive, terrain without
+/// material gets the [`TerrainMaterial::default`] and the used texture images
+/// are copied into `textures/`. The gameplay nodes go into `nodes.glb` and
+/// everything is listed in `<name>.area.toml`.
+fn build_area(cli: &WorldBuildCli) -> Result<AreaIndex, Box<dyn Error>> {
+    let area = SourceArea::read(&cli.input)?;
+    let name = match &cli.name {
+        Some(name) => name.clone(),
+        None => cli.input.file_stem().ok_or("the input has no file name")?.to_string_lossy().to_string(),
+    };
+
+    fs::create_dir_all(&cli.output)?;
+
+    let mut index = AreaIndex {
+        name,
+        chunk_size: cli.chunk_size,
+        nodes: None,
+        chunks: Vec::new(),
+    };
+    let mut used_images = BTreeSet::new();
+
+    for ((x, z), tile) in slice_terrain(&area.triangles, cli.chunk_size) {
+        let transform = Transform::from_xyz(x as f32, 0.0, z as f32);
+        let (collider_positions, collider_indices) = tile.collider_mesh();
+
+        for material in tile.primitives.keys().filter_map(|material| material.and_then(|material| area.materials.get(material))) {
+            used_images.extend(material.textures().map(|texture| texture.image));
+        }
+
+        // Every level doubles the cell size, the first level merges cells of 1/32 chunk.
+        let lods = (1..=cli.lod_levels)
+            .map(|level| (level, tile.decimate(cli.chunk_size as f32 / 64.0 * 2f32.powi(level as i32))))
+            .collect::<Vec<_>>();
+
+        let mut nodes = vec![terrain_node("terrain".to_string(), transform, tile, &area.materials)];
+        nodes.extend(lods.into_iter().map(|(level, lod)| terrain_node(format!("terrain_lod{}", level), transform, lod, &area.materials)));
+        nodes.push(GlbNode {
+            name: "terrain_col".to_string(),
+            transform,
+            mesh: Some(GlbMesh {
+                positions: collider_positions,
+                primitives: vec![GlbPrimitive { indices: collider_indices, material: None }],
+                ..default()
+            }),
+        });
+
+        let file = AreaIndex::chunk_file_name(x, z);
+        write_glb(&cli.output.join(&file), &nodes, &area.images)?;
+
+        index.chunks.push(AreaIndexChunk { x, z, file });
+    }
+
+    if !area.nodes.is_empty() {
+        let nodes = area.nodes.into_iter()
+            .map(|(name, transform)| GlbNode { name, transform, mesh: None })
+            .collect::<Vec<_>>();
+
+        write_glb(&cli.output.join("nodes.glb"), &nodes, &[])?;
+        index.nodes = Some("nodes.glb".to_string());
+    }
+
+    for image in used_images.into_iter().filter_map(|image| area.images.get(image)) {
+        let path = cli.output.join(&image.uri);
+        fs::create_dir_all(path.parent().ok_or("an image uri has no directory")?)?;
+        fs::write(path, &image.bytes)?;
+    }
+
+    fs::write(cli.output.join(format!("{}.area.toml", index.name)), toml::to_string(&index)?)?;
+    Ok(index)
+}
+
+/// Node of `tile` with one primitive per material, a missing material is the default one.
+fn terrain_node(name: String, transform: Transform, tile: TerrainTile, materials: &[TerrainMaterial]) -> GlbNode {
+    let primitives = tile.primitives.into_iter()
+        .map(|(material, indices)| GlbPrimitive {
+            indices,
+            material: Some(material.and_then(|material| materials.get(material)).cloned().unwrap_or_default()),
+        })
+        .collect();
+
+    GlbNode {
+        name,
+        transform,
+        mesh: Some(GlbMesh {
+            positions: tile.positions,
+            normals: tile.normals,
+            uvs: tile.uvs,
+            primitives,
+        }),
+    }
+}
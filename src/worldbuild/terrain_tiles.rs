@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, HashMap};
+use bevy::prelude::*;
+use crate::area_source::{TerrainTriangle, TerrainVertex};
+
+//################################################# Models #################################################
+/// Terrain of one chunk. The positions are relative to the chunk center so
+/// the chunk node only needs a translation.
+#[derive(Debug, Default)]
+pub struct TerrainTile {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    /// Triangle indices per material, every material is one primitive of
+    /// the chunk mesh. The primitives share the vertices.
+    pub primitives: BTreeMap<Option<usize>, Vec<u32>>,
+    vertex_lookup: HashMap<[u32; 8], u32>,
+}
+
+//################################################# Implementations #################################################
+impl TerrainTile {
+    fn push_triangle(&mut self, triangle: &TerrainTriangle, origin: Vec3) {
+        let indices = triangle.vertices.map(|vertex| self.push_vertex(&vertex, origin));
+        self.primitives.entry(triangle.material).or_default().extend(indices);
+    }
+
+    /// Triangles of every material.
+    pub fn triangles(&self) -> impl Iterator<Item = &[u32]> {
+        self.primitives.values().flat_map(|indices| indices.chunks_exact(3))
+    }
+
+    /// Push the vertex or reuse an equal one which is already in the tile.
+    fn push_vertex(&mut self, vertex: &TerrainVertex, origin: Vec3) -> u32 {
+        let position = (vertex.position - origin).to_array();
+        let normal = vertex.normal.to_array();
+        let uv = vertex.uv.to_array();
+
+        let mut key = [0; 8];
+        for (bits, value) in key.iter_mut().zip(position.iter().chain(normal.iter()).chain(uv.iter())) {
+            *bits = value.to_bits();
+        }
+
+        *self.vertex_lookup.entry(key).or_insert_with(|| {
+            self.positions.push(position);
+            self.normals.push(normal);
+            self.uvs.push(uv);
+            self.positions.len() as u32 - 1
+        })
+    }
+
+    /// Coarser copy of the tile for a level of detail. Vertices inside of the
+    /// same cell of `cell_size` are merged into their average and triangles
+    /// which collapse are removed, a material without triangles left is dropped.
+    pub fn decimate(&self, cell_size: f32) -> TerrainTile {
+        let mut cells = HashMap::new();
+        let mut clusters: Vec<(Vec3, Vec3, Vec2, f32)> = Vec::new();
+
+        let remap = self.positions.iter()
+            .map(|position| {
+                let cell = (Vec3::from(*position) / cell_size).floor().as_ivec3().to_array();
+                *cells.entry(cell).or_insert_with(|| {
+                    clusters.push((Vec3::ZERO, Vec3::ZERO, Vec2::ZERO, 0.0));
+                    clusters.len() as u32 - 1
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for (vertex, cluster) in remap.iter().enumerate() {
+            let (position, normal, uv, count) = &mut clusters[*cluster as usize];
+            *position += Vec3::from(self.positions[vertex]);
+            *normal += Vec3::from(self.normals[vertex]);
+            *uv += Vec2::from(self.uvs[vertex]);
+            *count += 1.0;
+        }
+
+        let mut tile = TerrainTile::default();
+        for (position, normal, uv, count) in clusters {
+            tile.positions.push((position / count).to_array());
+            tile.normals.push(normal.normalize_or(Vec3::Y).to_array());
+            tile.uvs.push((uv / count).to_array());
+        }
+
+        for (material, indices) in self.primitives.iter() {
+            let indices = indices.chunks_exact(3)
+                .map(|triangle| [triangle[0], triangle[1], triangle[2]].map(|index| remap[index as usize]))
+                .filter(|[a, b, c]| a != b && b != c && a != c)
+                .flatten()
+                .collect::<Vec<_>>();
+
+            if !indices.is_empty() {
+                tile.primitives.insert(*material, indices);
+            }
+        }
+
+        tile
+    }
+
+    /// Collision mesh of the tile. Vertices with the same position are welded
+    /// and triangles without area are removed, normals and uvs are not needed.
+    pub fn collider_mesh(&self) -> (Vec<[f32; 3]>, Vec<u32>) {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        let mut lookup = HashMap::new();
+
+        for triangle in self.triangles() {
+            let welded = triangle.iter()
+                .map(|index| {
+                    let position = self.positions[*index as usize];
+                    *lookup.entry(position.map(f32::to_bits)).or_insert_with(|| {
+                        positions.push(position);
+                        positions.len() as u32 - 1
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let [a, b, c] = [welded[0], welded[1], welded[2]].map(|index| Vec3::from(positions[index as usize]));
+            if (b - a).cross(c - a).length_squared() <= f32::EPSILON {
+                continue;
+            }
+
+            indices.extend(welded);
+        }
+
+        (positions, indices)
+    }
+}
+
+/// Split the triangles into tiles of `chunk_size`. A triangle belongs to the
+/// tile which contains his center, so no triangle is cut. The keys are the
+/// chunk centers like the chunk positions of `get_visible_chunks`.
+pub fn slice_terrain(triangles: &[TerrainTriangle], chunk_size: i32) -> BTreeMap<(i32, i32), TerrainTile> {
+    let mut tiles = BTreeMap::<(i32, i32), TerrainTile>::new();
+
+    for triangle in triangles {
+        let [a, b, c] = triangle.vertices.map(|vertex| vertex.position);
+        let center = (a + b + c) / 3.0;
+        let key = (tile_coordinate(center.x, chunk_size), tile_coordinate(center.z, chunk_size));
+
+        tiles.entry(key)
+            .or_default()
+            .push_triangle(triangle, Vec3::new(key.0 as f32, 0.0, key.1 as f32));
+    }
+
+    tiles
+}
+
+fn tile_coordinate(value: f32, chunk_size: i32) -> i32 {
+    (value / chunk_size as f32).round() as i32 * chunk_size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle(a: Vec3, b: Vec3, c: Vec3) -> TerrainTriangle {
+        let vertex = |position| TerrainVertex {
+            position,
+            normal: Vec3::Y,
+            uv: Vec2::ZERO,
+        };
+
+        TerrainTriangle {
+            vertices: [vertex(a), vertex(b), vertex(c)],
+            material: None,
+        }
+    }
+
+    /// Unit Test for check if triangles are sorted into the chunk of their center,
+    /// the positions are relative to the chunk center and every material is one primitive.
+    #[test]
+    fn test_slice_terrain() {
+        let mut triangles = [
+            triangle(Vec3::new(0.0, 1.0, 0.0), Vec3::new(10.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 10.0)),
+            triangle(Vec3::new(10.0, 1.0, 0.0), Vec3::new(10.0, 1.0, 10.0), Vec3::new(0.0, 1.0, 10.0)),
+            triangle(Vec3::new(500.0, 2.0, -300.0), Vec3::new(520.0, 2.0, -300.0), Vec3::new(500.0, 2.0, -280.0)),
+        ];
+        triangles[1].material = Some(1);
+
+        let tiles = slice_terrain(&triangles, 512);
+
+        assert_eq!(tiles.keys().copied().collect::<Vec<_>>(), vec![(0, 0), (512, -512)]);
+        assert_eq!(tiles[&(0, 0)].positions.len(), 4);
+        assert_eq!(tiles[&(0, 0)].triangles().count(), 2);
+        assert_eq!(tiles[&(0, 0)].primitives.keys().copied().collect::<Vec<_>>(), vec![None, Some(1)]);
+        assert_eq!(tiles[&(512, -512)].positions[0], [-12.0, 2.0, 212.0]);
+    }
+
+    /// Unit Test for check if decimation merges the vertices of a cell and drops collapsed triangles.
+    #[test]
+    fn test_decimate_tile() {
+        let triangles = [
+            triangle(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 40.0)),
+            triangle(Vec3::new(1.0, 0.0, 0.0), Vec3::new(40.0, 0.0, 40.0), Vec3::new(0.0, 0.0, 40.0)),
+        ];
+
+        let tiles = slice_terrain(&triangles, 512);
+        let tile = tiles[&(0, 0)].decimate(16.0);
+
+        assert_eq!(tile.primitives[&None], vec![0, 2, 1]);
+        assert_eq!(tile.positions[0], [0.5, 0.0, 0.0]);
+    }
+
+    /// Unit Test for check if the collider mesh welds vertices and drops triangles without area.
+    #[test]
+    fn test_collider_mesh_removes_degenerate_triangles() {
+        let triangles = [
+            triangle(Vec3::ZERO, Vec3::X, Vec3::Z),
+            triangle(Vec3::ZERO, Vec3::X, Vec3::X * 2.0),
+        ];
+
+        let tiles = slice_terrain(&triangles, 512);
+        let (positions, indices) = tiles[&(0, 0)].collider_mesh();
+
+        assert_eq!(positions.len(), 4);
+        assert_eq!(indices, vec![0, 1, 2]);
+    }
+}